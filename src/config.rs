@@ -0,0 +1,410 @@
+use std::{ env, fs, io::{ stdout, IsTerminal }, path::PathBuf, time::Duration };
+
+use sysinfo::Pid;
+
+use crate::{
+    constants::{
+        MAX_GRAPH_WINDOW_MS,
+        MAX_SAMPLE_INTERVAL_MS,
+        MIN_SAMPLE_INTERVAL_MS,
+        SAMPLE_INTERVAL_MS,
+    },
+    export::ExportFormat,
+    filter::parse_filter,
+    format::UnitMode,
+    process_columns::find_column,
+    graph::GraphStyle,
+    state::{ App, SortDirection, View },
+    utils::parse_duration,
+};
+
+pub const USAGE: &str = "\
+Usage: task-manager [command] [options]
+
+Commands:
+  tui                   Interactive process table (default)
+  ps                    Print the process table once and exit
+  batch                 Print the process table on every refresh, works without a terminal
+  export <file>         Write snapshots as json, csv or ndjson, picked by the file extension.
+                        csv and ndjson stream until interrupted or -n, \"-\" writes to stdout
+  record <file>         Record compressed snapshots to a file until interrupted or -n
+  replay <file>         Replay a recording, p plays/pauses, [ and ] step, +/- change speed
+                        and \":jump <time>\" jumps to a time
+
+Options:
+  -s, --sort <column>   Sort by column id, e.g. cpu, mem or name
+  -o, --order <order>   Sort direction, asc or desc
+  -f, --filter <query>  Initial filter, e.g. \"name ~ java and cpu > 20\"
+  -i, --interval <dur>  Refresh interval, e.g. 500ms, 2s or 1m
+  -p, --pid <pids>      Only show given comma separated PIDs, can be repeated
+  -u, --user <name>     Only show processes of the user
+  -n, --iterations <n>  Number of snapshots of batch, export and record
+  -w, --width <cols>    Cut lines of ps and batch to the width
+      --format <format> Export format when the extension does not tell: json, csv or ndjson
+      --view <view>     Initial view: processes, performance, disks, network
+                        or sensors
+      --graph <style>   Performance graph characters: braille or block
+      --window <dur>    Time span of the performance graphs, e.g. 5m, at most 1h
+      --units <mode>    Byte units: iec (KiB, MiB) or si (KB, MB)
+      --color <mode>    Colors: auto, always or never
+      --sysfs <dir>     Directory sysfs is read from for GPUs, /sys by default
+  -h, --help            Print this help
+  -V, --version         Print version
+
+Options can also be set in $XDG_CONFIG_HOME/task-manager/config as \"option = value\" lines,
+e.g. \"interval = 2s\". Command line arguments override the config file.";
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq)]
+pub enum Command {
+    TUI,
+    PS,
+    BATCH,
+    EXPORT(PathBuf),
+    RECORD(PathBuf),
+    REPLAY(PathBuf),
+    HELP,
+    VERSION,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Clone, Copy)]
+pub enum ColorMode {
+    /// Colors when printing to a terminal
+    AUTO,
+    ALWAYS,
+    NEVER,
+}
+
+/// Settings from the config file, overridden by command line arguments
+pub struct Config {
+    pub command: Command,
+    pub interval: Duration,
+    /// Index of the sort column in PROCESS_COLUMNS
+    pub sort_column: Option<usize>,
+    pub sort_direction: Option<SortDirection>,
+    pub filter: String,
+    pub pids: Vec<Pid>,
+    pub user: Option<String>,
+    pub color: ColorMode,
+    /// Number of tables printed in batch mode, None runs until interrupted
+    pub iterations: Option<u64>,
+    /// Line width of ps and batch output, None prints full lines
+    pub width: Option<usize>,
+    pub export_format: Option<ExportFormat>,
+    pub view: Option<View>,
+    pub graph_style: Option<GraphStyle>,
+    pub graph_window: Option<Duration>,
+    pub unit_mode: Option<UnitMode>,
+    /// Where the sampler reads GPUs from, a copy of the sysfs layout can be used instead
+    pub sysfs_root: PathBuf,
+}
+
+impl Config {
+    /// Reads the config file if there is one and applies the command line arguments on top
+    pub fn load(args: &[String]) -> Result<Config, String> {
+        let mut config = Config {
+            command: Command::TUI,
+            interval: Duration::from_millis(SAMPLE_INTERVAL_MS),
+            sort_column: None,
+            sort_direction: None,
+            filter: String::new(),
+            pids: Vec::new(),
+            user: None,
+            color: ColorMode::AUTO,
+            iterations: None,
+            width: None,
+            export_format: None,
+            view: None,
+            graph_style: None,
+            graph_window: None,
+            unit_mode: None,
+            sysfs_root: PathBuf::from("/sys"),
+        };
+        if let Some(path) = get_config_path() {
+            match fs::read_to_string(&path) {
+                Ok(text) => {
+                    config
+                        .apply_file(&text)
+                        .map_err(|error| format!("{}: {}", path.display(), error))?;
+                }
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+                Err(error) => {
+                    return Err(format!("{}: {}", path.display(), error));
+                }
+            }
+        }
+        config.apply_args(args)?;
+        return Ok(config);
+    }
+
+    /// Applies "key = value" lines, empty lines and lines starting with # are skipped
+    fn apply_file(&mut self, text: &str) -> Result<(), String> {
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected 'key = value'", i + 1));
+            };
+            self.set(key.trim(), value.trim()).map_err(|error| format!("line {}: {}", i + 1, error))?;
+        }
+        return Ok(());
+    }
+
+    fn apply_args(&mut self, args: &[String]) -> Result<(), String> {
+        let mut args = args.iter().peekable();
+        // The command is only accepted as the first argument
+        if let Some(command) = args.next_if(|arg| !arg.starts_with('-')) {
+            self.command = match command.as_str() {
+                "tui" => Command::TUI,
+                "ps" => Command::PS,
+                "batch" => Command::BATCH,
+                "export" | "record" | "replay" => {
+                    let Some(path) = args.next() else {
+                        return Err(format!("Missing file for {command}"));
+                    };
+                    let path = PathBuf::from(path);
+                    match command.as_str() {
+                        "export" => Command::EXPORT(path),
+                        "record" => Command::RECORD(path),
+                        _ => Command::REPLAY(path),
+                    }
+                }
+                _ => {
+                    return Err(format!("Unknown command '{command}'"));
+                }
+            };
+        }
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+                _ => (arg.as_str(), None),
+            };
+            let key = match flag {
+                "-h" | "--help" => {
+                    self.command = Command::HELP;
+                    return Ok(());
+                }
+                "-V" | "--version" => {
+                    self.command = Command::VERSION;
+                    return Ok(());
+                }
+                "-s" | "--sort" => "sort",
+                "-o" | "--order" => "order",
+                "-f" | "--filter" => "filter",
+                "-i" | "--interval" => "interval",
+                "-p" | "--pid" => "pid",
+                "-u" | "--user" => "user",
+                "-n" | "--iterations" => "iterations",
+                "-w" | "--width" => "width",
+                "--color" => "color",
+                "--format" => "format",
+                "--view" => "view",
+                "--graph" => "graph",
+                "--window" => "graph_window",
+                "--units" => "units",
+                "--sysfs" => "sysfs",
+                _ => {
+                    return Err(format!("Unknown argument '{arg}'"));
+                }
+            };
+            let value = match inline_value {
+                Some(value) => value,
+                None =>
+                    match args.next() {
+                        Some(value) => value.as_str(),
+                        None => {
+                            return Err(format!("Missing value for {flag}"));
+                        }
+                    }
+            };
+            self.set(key, value)?;
+        }
+        return Ok(());
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "interval" => {
+                self.interval = parse_interval(value)?;
+            }
+            "sort" => {
+                let Some(column) = find_column(value) else {
+                    return Err(format!("Unknown column '{value}'"));
+                };
+                self.sort_column = Some(column);
+            }
+            "order" => {
+                self.sort_direction = match value.to_lowercase().as_str() {
+                    "asc" => Some(SortDirection::ASC),
+                    "desc" => Some(SortDirection::DESC),
+                    _ => {
+                        return Err(format!("Unknown order '{value}', expected asc or desc"));
+                    }
+                };
+            }
+            "filter" => {
+                // Checked early, so that errors are reported before the terminal is taken over
+                parse_filter(value, false, false).map_err(|error| {
+                    format!("Invalid filter: {error}")
+                })?;
+                self.filter = value.to_string();
+            }
+            "pid" => {
+                for pid in value.split(',').filter(|pid| !pid.is_empty()) {
+                    match pid.trim().parse::<u32>() {
+                        Ok(pid) => self.pids.push(Pid::from_u32(pid)),
+                        Err(_) => {
+                            return Err(format!("Invalid PID '{pid}'"));
+                        }
+                    }
+                }
+            }
+            "iterations" => {
+                match value.parse::<u64>() {
+                    Ok(iterations) if iterations > 0 => {
+                        self.iterations = Some(iterations);
+                    }
+                    _ => {
+                        return Err(format!("Invalid iteration count '{value}'"));
+                    }
+                }
+            }
+            "width" => {
+                match value.parse::<usize>() {
+                    Ok(width) if width > 0 => {
+                        self.width = Some(width);
+                    }
+                    _ => {
+                        return Err(format!("Invalid width '{value}'"));
+                    }
+                }
+            }
+            "format" => {
+                let Some(format) = ExportFormat::from_name(value) else {
+                    return Err(format!("Unknown format '{value}', expected json, csv or ndjson"));
+                };
+                self.export_format = Some(format);
+            }
+            "view" => {
+                let Some(view) = View::from_name(value) else {
+                    return Err(format!("Unknown view '{value}'"));
+                };
+                self.view = Some(view);
+            }
+            "graph" => {
+                let Some(graph_style) = GraphStyle::from_name(value) else {
+                    return Err(format!("Unknown graph style '{value}', expected braille or block"));
+                };
+                self.graph_style = Some(graph_style);
+            }
+            "graph_window" => {
+                self.graph_window = Some(parse_graph_window(value)?);
+            }
+            "units" => {
+                let Some(unit_mode) = UnitMode::from_name(value) else {
+                    return Err(format!("Unknown unit mode '{value}', expected iec or si"));
+                };
+                self.unit_mode = Some(unit_mode);
+            }
+            "sysfs" => {
+                self.sysfs_root = PathBuf::from(value);
+            }
+            "user" => {
+                self.user = Some(value.to_string());
+            }
+            "color" => {
+                self.color = match value.to_lowercase().as_str() {
+                    "auto" => ColorMode::AUTO,
+                    "always" => ColorMode::ALWAYS,
+                    "never" => ColorMode::NEVER,
+                    _ => {
+                        return Err(
+                            format!("Unknown color mode '{value}', expected auto, always or never")
+                        );
+                    }
+                };
+            }
+            _ => {
+                return Err(format!("Unknown option '{key}'"));
+            }
+        }
+        return Ok(());
+    }
+
+    /// Sets up the initial state from the options
+    pub fn apply(&self, app: &mut App) {
+        if let Some(column) = self.sort_column {
+            // The process table is sorted by the selected column
+            app.selected_column = match app.visible_columns.iter().position(|id| *id == column) {
+                Some(position) => position,
+                None => {
+                    app.visible_columns.push(column);
+                    app.visible_columns.len() - 1
+                }
+            };
+        }
+        if let Some(sort_direction) = self.sort_direction {
+            app.sort_direction = sort_direction;
+        }
+        app.search_text = self.filter.to_string();
+        app.search_filter = parse_filter(&self.filter, false, false).unwrap_or_default();
+        app.watched_pids = self.pids.clone();
+        if let Some(view) = self.view {
+            app.view = view;
+        }
+        if let Some(graph_style) = self.graph_style {
+            app.graph_style = graph_style;
+        }
+        if let Some(graph_window) = self.graph_window {
+            app.graph_window_ms = graph_window.as_millis() as u64;
+        }
+        if let Some(unit_mode) = self.unit_mode {
+            app.unit_mode = unit_mode;
+        }
+        app.user_filter = self.user.clone();
+        colored::control::set_override(
+            match self.color {
+                ColorMode::AUTO => stdout().is_terminal(),
+                ColorMode::ALWAYS => true,
+                ColorMode::NEVER => false,
+            }
+        );
+    }
+}
+
+/// Returns $XDG_CONFIG_HOME/task-manager/config, falling back to ~/.config
+fn get_config_path() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    return Some(config_home.join("task-manager").join("config"));
+}
+
+/// Parses a sampling interval, rejecting intervals outside the supported range
+pub fn parse_interval(text: &str) -> Result<Duration, String> {
+    let interval = parse_duration(text)?;
+    let range = MIN_SAMPLE_INTERVAL_MS..=MAX_SAMPLE_INTERVAL_MS;
+    if !range.contains(&(interval.as_millis() as u64)) {
+        return Err(
+            format!(
+                "Interval must be between {}ms and {}s",
+                MIN_SAMPLE_INTERVAL_MS,
+                MAX_SAMPLE_INTERVAL_MS / 1000
+            )
+        );
+    }
+    return Ok(interval);
+}
+
+/// Parses the time span of the performance graphs, at least a second and at most MAX_GRAPH_WINDOW_MS
+pub fn parse_graph_window(text: &str) -> Result<Duration, String> {
+    let window = parse_duration(text)?;
+    if window < Duration::from_secs(1) || window.as_millis() > (MAX_GRAPH_WINDOW_MS as u128) {
+        return Err(format!("Graph window must be between 1s and {}m", MAX_GRAPH_WINDOW_MS / 60_000));
+    }
+    return Ok(window);
+}
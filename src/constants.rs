@@ -1,4 +1,18 @@
-pub const PROCESS_HEADERS: [&str; 5] = ["PID", "Name", "CPU (%)", "Memory (KB)", "Run time (s)"];
-pub const PROCESS_HEADERS_LEN: usize = PROCESS_HEADERS.len();
 pub const COLUMN_SEPARATOR: &str = " ";
 pub const CYCLE_WAIT_TIME_MS: u64 = 200;
+/// Default interval between system snapshots taken by the sampler thread
+pub const SAMPLE_INTERVAL_MS: u64 = 1000;
+pub const MIN_SAMPLE_INTERVAL_MS: u64 = 100;
+pub const MAX_SAMPLE_INTERVAL_MS: u64 = 3_600_000;
+/// Longest wait for input before checking for a new snapshot
+pub const INPUT_POLL_TIME_MS: u64 = 50;
+/// Samples kept per process for sparklines
+pub const HISTORY_LENGTH: usize = 120;
+/// How long the history of an exited process is kept, e.g. for the detail view
+pub const HISTORY_GRACE_PERIOD_MS: u64 = 30_000;
+/// Width of sparkline columns outside the process table
+pub const SPARKLINE_WIDTH: usize = 20;
+/// Time span of the performance graphs
+pub const GRAPH_WINDOW_MS: u64 = 300_000;
+/// Longest time span the performance graphs can be set to, older samples are dropped
+pub const MAX_GRAPH_WINDOW_MS: u64 = 3_600_000;
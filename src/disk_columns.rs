@@ -0,0 +1,130 @@
+use std::cmp::Ordering;
+
+use crate::{
+    format::{ format_bytes, format_percent, format_rate, UnitMode },
+    history::DiskRates,
+    process_columns::{ Alignment, SortKey },
+    snapshot::DiskSnapshot,
+};
+
+const USAGE_BAR_WIDTH: usize = 5;
+
+/// Disk of the snapshot with its throughput, rates are None until a second snapshot is recorded
+pub struct DiskRow<'a> {
+    pub disk: &'a DiskSnapshot,
+    pub rates: Option<DiskRates>,
+}
+
+impl DiskRow<'_> {
+    fn used_space(&self) -> u64 {
+        return self.disk.total_space.saturating_sub(self.disk.available_space);
+    }
+
+    fn usage(&self) -> f64 {
+        if self.disk.total_space == 0 {
+            return 0.0;
+        }
+        return (self.used_space() as f64) / (self.disk.total_space as f64);
+    }
+}
+
+pub struct DiskColumn {
+    /// Identifier used for picking the column from the command line
+    pub id: &'static str,
+    pub header: &'static str,
+    pub alignment: Alignment,
+    /// Text printed in the table cell
+    pub value: fn(&DiskRow, UnitMode) -> String,
+    pub sort_key: fn(&DiskRow) -> SortKey,
+}
+
+impl DiskColumn {
+    pub fn compare(&self, a: &DiskRow, b: &DiskRow) -> Ordering {
+        return (self.sort_key)(a).compare(&(self.sort_key)(b));
+    }
+}
+
+pub fn find_disk_column(id: &str) -> Option<usize> {
+    return DISK_COLUMNS.iter().position(|column| column.id == id.to_lowercase());
+}
+
+/// Bytes per second, "-" before the rate is known
+fn format_optional_rate(rate: Option<f64>, units: UnitMode) -> String {
+    return rate.map_or(String::from("-"), |rate| format_rate(rate, units));
+}
+
+fn format_usage_bar(usage: f64) -> String {
+    let filled = ((usage * (USAGE_BAR_WIDTH as f64)).round() as usize).min(USAGE_BAR_WIDTH);
+    return format!(
+        "[{}{}] {}",
+        "|".repeat(filled),
+        " ".repeat(USAGE_BAR_WIDTH - filled),
+        format_percent(usage * 100.0)
+    );
+}
+
+pub const DISK_COLUMNS: [DiskColumn; 9] = [
+    DiskColumn {
+        id: "mount",
+        header: "Mount point",
+        alignment: Alignment::LEFT,
+        value: |row, _| row.disk.mount_point.to_string(),
+        sort_key: |row| SortKey::TEXT(row.disk.mount_point.to_string()),
+    },
+    DiskColumn {
+        id: "device",
+        header: "Device",
+        alignment: Alignment::LEFT,
+        value: |row, _| row.disk.name.to_string(),
+        sort_key: |row| SortKey::TEXT(row.disk.name.to_string()),
+    },
+    DiskColumn {
+        id: "fs",
+        header: "Filesystem",
+        alignment: Alignment::LEFT,
+        value: |row, _| row.disk.file_system.to_string(),
+        sort_key: |row| SortKey::TEXT(row.disk.file_system.to_string()),
+    },
+    DiskColumn {
+        id: "size",
+        header: "Size",
+        alignment: Alignment::RIGHT,
+        value: |row, units| format_bytes(row.disk.total_space, units),
+        sort_key: |row| SortKey::NUMBER(row.disk.total_space as f64),
+    },
+    DiskColumn {
+        id: "used",
+        header: "Used",
+        alignment: Alignment::RIGHT,
+        value: |row, units| format_bytes(row.used_space(), units),
+        sort_key: |row| SortKey::NUMBER(row.used_space() as f64),
+    },
+    DiskColumn {
+        id: "available",
+        header: "Available",
+        alignment: Alignment::RIGHT,
+        value: |row, units| format_bytes(row.disk.available_space, units),
+        sort_key: |row| SortKey::NUMBER(row.disk.available_space as f64),
+    },
+    DiskColumn {
+        id: "usage",
+        header: "Usage",
+        alignment: Alignment::LEFT,
+        value: |row, _| format_usage_bar(row.usage()),
+        sort_key: |row| SortKey::NUMBER(row.usage()),
+    },
+    DiskColumn {
+        id: "read",
+        header: "Read",
+        alignment: Alignment::RIGHT,
+        value: |row, units| format_optional_rate(row.rates.map(|rates| rates.read), units),
+        sort_key: |row| SortKey::NUMBER(row.rates.map_or(-1.0, |rates| rates.read)),
+    },
+    DiskColumn {
+        id: "write",
+        header: "Write",
+        alignment: Alignment::RIGHT,
+        value: |row, units| format_optional_rate(row.rates.map(|rates| rates.written), units),
+        sort_key: |row| SortKey::NUMBER(row.rates.map_or(-1.0, |rates| rates.written)),
+    },
+];
@@ -0,0 +1,674 @@
+use std::{
+    borrow::Cow,
+    fs::{ File, OpenOptions },
+    io::{ stdout, BufWriter, Write },
+    path::Path,
+};
+
+use serde::{ Deserialize, Serialize };
+use sysinfo::Pid;
+
+use crate::{ process_list::get_process_rows, snapshot::{
+        CpuSnapshot,
+        DiskSnapshot,
+        GpuSnapshot,
+        HostSnapshot,
+        NetworkSnapshot,
+        ProcessSnapshot,
+        SensorSnapshot,
+        Snapshot,
+        StateCounts,
+    }, state::App };
+
+/// Increased whenever a field is added, renamed, removed or changes its meaning
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Clone, Copy)]
+pub enum ExportFormat {
+    /// One pretty printed object holding a single snapshot
+    JSON,
+    /// One row per process, with the system values repeated on every row
+    CSV,
+    /// One object per snapshot on each line
+    NDJSON,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        return match name.to_lowercase().as_str() {
+            "json" => Some(ExportFormat::JSON),
+            "csv" => Some(ExportFormat::CSV),
+            "ndjson" | "jsonl" => Some(ExportFormat::NDJSON),
+            _ => None,
+        };
+    }
+
+    /// Picks the format from the file extension
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        return ExportFormat::from_name(path.extension()?.to_str()?);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedCpu {
+    /// Usage in percent
+    usage: f32,
+    /// MHz
+    frequency: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedGpu<'a> {
+    /// Vendor
+    name: Cow<'a, str>,
+    /// Utilization in percent, null when the driver does not report it
+    usage: Option<u32>,
+    /// Degrees Celsius
+    temperature: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedDisk<'a> {
+    name: Cow<'a, str>,
+    mount_point: Cow<'a, str>,
+    file_system: Cow<'a, str>,
+    /// Bytes
+    total_space: u64,
+    available_space: u64,
+    /// Bytes since boot, null when the device has no I/O statistics
+    total_read_bytes: Option<u64>,
+    total_written_bytes: Option<u64>,
+}
+
+/// Counters since boot
+#[derive(Serialize, Deserialize)]
+struct ExportedNetwork<'a> {
+    name: Cow<'a, str>,
+    /// Bytes
+    total_received: u64,
+    total_transmitted: u64,
+    total_packets_received: u64,
+    total_packets_transmitted: u64,
+    total_errors_received: u64,
+    total_errors_transmitted: u64,
+}
+
+/// Degrees Celsius
+#[derive(Serialize, Deserialize)]
+struct ExportedSensor<'a> {
+    label: Cow<'a, str>,
+    temperature: f32,
+    max: f32,
+    critical: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedHost<'a> {
+    host_name: Cow<'a, str>,
+    kernel_version: Cow<'a, str>,
+    os_version: Cow<'a, str>,
+    /// Seconds since epoch
+    boot_time: u64,
+    /// Seconds since boot
+    uptime: u64,
+    /// 1, 5 and 15 minute load averages
+    load_average: [f64; 3],
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedStateCounts {
+    total: usize,
+    running: usize,
+    sleeping: usize,
+    stopped: usize,
+    zombie: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedProcess<'a> {
+    pid: u32,
+    parent_pid: Option<u32>,
+    name: Cow<'a, str>,
+    user: Cow<'a, str>,
+    status: Cow<'a, str>,
+    cpu_usage: f32,
+    /// Resident memory in bytes
+    memory: u64,
+    /// Virtual memory in bytes
+    virtual_memory: u64,
+    /// Seconds since the process started
+    run_time: u64,
+    /// Seconds since epoch
+    start_time: u64,
+    threads: usize,
+    read_bytes: u64,
+    written_bytes: u64,
+    nice: Option<i64>,
+    command: Cow<'a, str>,
+    executable: Cow<'a, str>,
+}
+
+/// Snapshot layout of the JSON and NDJSON exports and recordings
+#[derive(Serialize, Deserialize)]
+pub struct ExportedSnapshot<'a> {
+    schema_version: u32,
+    /// Milliseconds since epoch
+    timestamp_ms: u64,
+    /// Global CPU usage in percent
+    cpu_usage: f32,
+    /// Logical cores
+    cpus: Vec<ExportedCpu>,
+    /// Bytes
+    used_memory: u64,
+    total_memory: u64,
+    used_swap: u64,
+    total_swap: u64,
+    /// Null when no graphics cards were found
+    gpus: Option<Vec<ExportedGpu<'a>>>,
+    disks: Vec<ExportedDisk<'a>>,
+    networks: Vec<ExportedNetwork<'a>>,
+    sensors: Vec<ExportedSensor<'a>>,
+    host: ExportedHost<'a>,
+    process_counts: ExportedStateCounts,
+    thread_counts: ExportedStateCounts,
+    processes: Vec<ExportedProcess<'a>>,
+}
+
+/// CSV row, CSV cannot nest so the process fields are repeated here.
+/// Cores, disks, networks, sensors and GPUs are left out.
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    schema_version: u32,
+    timestamp_ms: u64,
+    system_cpu_usage: f32,
+    system_used_memory: u64,
+    system_total_memory: u64,
+    pid: u32,
+    parent_pid: Option<u32>,
+    name: &'a str,
+    user: &'a str,
+    status: &'a str,
+    cpu_usage: f32,
+    memory: u64,
+    virtual_memory: u64,
+    run_time: u64,
+    start_time: u64,
+    threads: usize,
+    read_bytes: u64,
+    written_bytes: u64,
+    nice: Option<i64>,
+    command: &'a str,
+    executable: &'a str,
+}
+
+fn export_process(process: &ProcessSnapshot) -> ExportedProcess<'_> {
+    return ExportedProcess {
+        pid: process.pid.as_u32(),
+        parent_pid: process.parent.map(|parent| parent.as_u32()),
+        name: Cow::Borrowed(&process.name),
+        user: Cow::Borrowed(&process.user),
+        status: Cow::Borrowed(&process.status),
+        cpu_usage: process.cpu_usage,
+        memory: process.memory,
+        virtual_memory: process.virtual_memory,
+        run_time: process.run_time,
+        start_time: process.start_time,
+        threads: process.threads,
+        read_bytes: process.read_bytes,
+        written_bytes: process.written_bytes,
+        nice: process.nice,
+        command: Cow::Borrowed(&process.command),
+        executable: Cow::Borrowed(&process.executable),
+    };
+}
+
+fn export_disk(disk: &DiskSnapshot) -> ExportedDisk<'_> {
+    return ExportedDisk {
+        name: Cow::Borrowed(&disk.name),
+        mount_point: Cow::Borrowed(&disk.mount_point),
+        file_system: Cow::Borrowed(&disk.file_system),
+        total_space: disk.total_space,
+        available_space: disk.available_space,
+        total_read_bytes: disk.total_read_bytes,
+        total_written_bytes: disk.total_written_bytes,
+    };
+}
+
+fn import_disk(disk: ExportedDisk) -> DiskSnapshot {
+    return DiskSnapshot {
+        name: disk.name.into_owned(),
+        mount_point: disk.mount_point.into_owned(),
+        file_system: disk.file_system.into_owned(),
+        total_space: disk.total_space,
+        available_space: disk.available_space,
+        total_read_bytes: disk.total_read_bytes,
+        total_written_bytes: disk.total_written_bytes,
+    };
+}
+
+fn export_network(network: &NetworkSnapshot) -> ExportedNetwork<'_> {
+    return ExportedNetwork {
+        name: Cow::Borrowed(&network.name),
+        total_received: network.total_received,
+        total_transmitted: network.total_transmitted,
+        total_packets_received: network.total_packets_received,
+        total_packets_transmitted: network.total_packets_transmitted,
+        total_errors_received: network.total_errors_received,
+        total_errors_transmitted: network.total_errors_transmitted,
+    };
+}
+
+fn import_network(network: ExportedNetwork) -> NetworkSnapshot {
+    return NetworkSnapshot {
+        name: network.name.into_owned(),
+        total_received: network.total_received,
+        total_transmitted: network.total_transmitted,
+        total_packets_received: network.total_packets_received,
+        total_packets_transmitted: network.total_packets_transmitted,
+        total_errors_received: network.total_errors_received,
+        total_errors_transmitted: network.total_errors_transmitted,
+    };
+}
+
+fn export_counts(counts: &StateCounts) -> ExportedStateCounts {
+    return ExportedStateCounts {
+        total: counts.total,
+        running: counts.running,
+        sleeping: counts.sleeping,
+        stopped: counts.stopped,
+        zombie: counts.zombie,
+    };
+}
+
+fn import_counts(counts: ExportedStateCounts) -> StateCounts {
+    return StateCounts {
+        total: counts.total,
+        running: counts.running,
+        sleeping: counts.sleeping,
+        stopped: counts.stopped,
+        zombie: counts.zombie,
+    };
+}
+
+fn import_process(process: ExportedProcess) -> ProcessSnapshot {
+    return ProcessSnapshot {
+        pid: Pid::from_u32(process.pid),
+        name: process.name.into_owned(),
+        cpu_usage: process.cpu_usage,
+        memory: process.memory,
+        virtual_memory: process.virtual_memory,
+        run_time: process.run_time,
+        start_time: process.start_time,
+        user: process.user.into_owned(),
+        status: process.status.into_owned(),
+        parent: process.parent_pid.map(Pid::from_u32),
+        threads: process.threads,
+        read_bytes: process.read_bytes,
+        written_bytes: process.written_bytes,
+        command: process.command.into_owned(),
+        executable: process.executable.into_owned(),
+        nice: process.nice,
+    };
+}
+
+impl ExportedSnapshot<'_> {
+    /// Converts a deserialized snapshot back, fails for snapshots of another schema version
+    pub fn import(self) -> Result<Snapshot, String> {
+        if self.schema_version != SCHEMA_VERSION {
+            return Err(
+                format!(
+                    "Unsupported schema version {}, expected {}",
+                    self.schema_version,
+                    SCHEMA_VERSION
+                )
+            );
+        }
+        return Ok(Snapshot {
+            timestamp_ms: self.timestamp_ms,
+            processes: self.processes.into_iter().map(import_process).collect(),
+            global_cpu_usage: self.cpu_usage,
+            cpus: self.cpus
+                .into_iter()
+                .map(|cpu| CpuSnapshot { usage: cpu.usage, frequency: cpu.frequency })
+                .collect(),
+            used_memory: self.used_memory,
+            total_memory: self.total_memory,
+            used_swap: self.used_swap,
+            total_swap: self.total_swap,
+            gpus: self.gpus.map(|gpus| {
+                return gpus
+                    .into_iter()
+                    .map(|gpu| GpuSnapshot {
+                        name: gpu.name.into_owned(),
+                        usage: gpu.usage,
+                        temperature: gpu.temperature,
+                    })
+                    .collect();
+            }),
+            disks: self.disks.into_iter().map(import_disk).collect(),
+            networks: self.networks.into_iter().map(import_network).collect(),
+            sensors: self.sensors
+                .into_iter()
+                .map(|sensor| SensorSnapshot {
+                    label: sensor.label.into_owned(),
+                    temperature: sensor.temperature,
+                    max: sensor.max,
+                    critical: sensor.critical,
+                })
+                .collect(),
+            host: HostSnapshot {
+                host_name: self.host.host_name.into_owned(),
+                kernel_version: self.host.kernel_version.into_owned(),
+                os_version: self.host.os_version.into_owned(),
+                boot_time: self.host.boot_time,
+                uptime: self.host.uptime,
+                load_average: self.host.load_average,
+            },
+            process_counts: import_counts(self.process_counts),
+            thread_counts: import_counts(self.thread_counts),
+        });
+    }
+}
+
+/// Processes shown by the current filter and sort order
+fn get_exported_processes<'a>(snapshot: &'a Snapshot, app: &App) -> Vec<&'a ProcessSnapshot> {
+    return get_process_rows(snapshot, app)
+        .into_iter()
+        .map(|(process, _)| process)
+        .collect();
+}
+
+/// Snapshot with the processes shown by the current filter and sort order
+pub fn export_snapshot<'a>(snapshot: &'a Snapshot, app: &App) -> ExportedSnapshot<'a> {
+    return export_snapshot_with_processes(snapshot, get_exported_processes(snapshot, app));
+}
+
+/// Snapshot with all processes as sampled, e.g. for recordings that are filtered on replay
+pub fn export_unfiltered_snapshot(snapshot: &Snapshot) -> ExportedSnapshot<'_> {
+    return export_snapshot_with_processes(snapshot, snapshot.processes.iter().collect());
+}
+
+fn export_snapshot_with_processes<'a>(
+    snapshot: &'a Snapshot,
+    processes: Vec<&'a ProcessSnapshot>
+) -> ExportedSnapshot<'a> {
+    return ExportedSnapshot {
+        schema_version: SCHEMA_VERSION,
+        timestamp_ms: snapshot.timestamp_ms,
+        cpu_usage: snapshot.global_cpu_usage,
+        cpus: snapshot.cpus
+            .iter()
+            .map(|cpu| ExportedCpu { usage: cpu.usage, frequency: cpu.frequency })
+            .collect(),
+        used_memory: snapshot.used_memory,
+        total_memory: snapshot.total_memory,
+        used_swap: snapshot.used_swap,
+        total_swap: snapshot.total_swap,
+        gpus: snapshot.gpus.as_ref().map(|gpus| {
+            return gpus
+                .iter()
+                .map(|gpu| ExportedGpu {
+                    name: Cow::Borrowed(&gpu.name),
+                    usage: gpu.usage,
+                    temperature: gpu.temperature,
+                })
+                .collect();
+        }),
+        disks: snapshot.disks.iter().map(export_disk).collect(),
+        networks: snapshot.networks.iter().map(export_network).collect(),
+        sensors: snapshot.sensors
+            .iter()
+            .map(|sensor| ExportedSensor {
+                label: Cow::Borrowed(&sensor.label),
+                temperature: sensor.temperature,
+                max: sensor.max,
+                critical: sensor.critical,
+            })
+            .collect(),
+        host: ExportedHost {
+            host_name: Cow::Borrowed(&snapshot.host.host_name),
+            kernel_version: Cow::Borrowed(&snapshot.host.kernel_version),
+            os_version: Cow::Borrowed(&snapshot.host.os_version),
+            boot_time: snapshot.host.boot_time,
+            uptime: snapshot.host.uptime,
+            load_average: snapshot.host.load_average,
+        },
+        process_counts: export_counts(&snapshot.process_counts),
+        thread_counts: export_counts(&snapshot.thread_counts),
+        processes: processes.into_iter().map(export_process).collect(),
+    };
+}
+
+/// Writes snapshots to a file or stdout, one after another for CSV and NDJSON
+pub struct SnapshotWriter {
+    format: ExportFormat,
+    out: BufWriter<Box<dyn Write>>,
+    /// CSV header is only written before the first rows
+    write_header: bool,
+}
+
+impl SnapshotWriter {
+    /// Opens the path for writing, "-" writes to stdout. NDJSON is appended to existing files,
+    /// other formats replace them.
+    pub fn create(path: &Path, format: ExportFormat) -> Result<SnapshotWriter, String> {
+        let out: Box<dyn Write> = if path == Path::new("-") {
+            Box::new(stdout())
+        } else if format == ExportFormat::NDJSON {
+            Box::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|error| format!("{}: {}", path.display(), error))?
+            )
+        } else {
+            Box::new(File::create(path).map_err(|error| format!("{}: {}", path.display(), error))?)
+        };
+        return Ok(SnapshotWriter { format, out: BufWriter::new(out), write_header: true });
+    }
+
+    /// Writes the snapshot with the processes shown by the current filter and sort order
+    pub fn write(&mut self, snapshot: &Snapshot, app: &App) -> Result<(), String> {
+        let result = match self.format {
+            ExportFormat::JSON => {
+                serde_json
+                    ::to_writer_pretty(&mut self.out, &export_snapshot(snapshot, app))
+                    .map_err(|error| error.to_string())
+                    .and_then(|_| writeln!(self.out).map_err(|error| error.to_string()))
+            }
+            ExportFormat::NDJSON => {
+                serde_json
+                    ::to_writer(&mut self.out, &export_snapshot(snapshot, app))
+                    .map_err(|error| error.to_string())
+                    .and_then(|_| writeln!(self.out).map_err(|error| error.to_string()))
+            }
+            ExportFormat::CSV => self.write_csv(snapshot, &get_exported_processes(snapshot, app)),
+        };
+        result?;
+        return self.out.flush().map_err(|error| error.to_string());
+    }
+
+    fn write_csv(&mut self, snapshot: &Snapshot, processes: &[&ProcessSnapshot]) -> Result<(), String> {
+        let mut writer = csv::WriterBuilder
+            ::new()
+            .has_headers(self.write_header)
+            .from_writer(&mut self.out);
+        for process in processes {
+            writer
+                .serialize(CsvRow {
+                    schema_version: SCHEMA_VERSION,
+                    timestamp_ms: snapshot.timestamp_ms,
+                    system_cpu_usage: snapshot.global_cpu_usage,
+                    system_used_memory: snapshot.used_memory,
+                    system_total_memory: snapshot.total_memory,
+                    pid: process.pid.as_u32(),
+                    parent_pid: process.parent.map(|parent| parent.as_u32()),
+                    name: &process.name,
+                    user: &process.user,
+                    status: &process.status,
+                    cpu_usage: process.cpu_usage,
+                    memory: process.memory,
+                    virtual_memory: process.virtual_memory,
+                    run_time: process.run_time,
+                    start_time: process.start_time,
+                    threads: process.threads,
+                    read_bytes: process.read_bytes,
+                    written_bytes: process.written_bytes,
+                    nice: process.nice,
+                    command: &process.command,
+                    executable: &process.executable,
+                })
+                .map_err(|error| error.to_string())?;
+        }
+        writer.flush().map_err(|error| error.to_string())?;
+        // An empty table writes no header, so it is still written with the next rows
+        self.write_header = self.write_header && processes.is_empty();
+        return Ok(());
+    }
+}
+
+/// Writes snapshots until the iterations are done or, without iterations, until interrupted.
+/// JSON holds a single snapshot.
+pub fn run_export(app: &mut App, path: &Path, format: ExportFormat, iterations: Option<u64>) -> Result<(), String> {
+    let iterations = match format {
+        ExportFormat::JSON if iterations.is_some_and(|iterations| iterations > 1) => {
+            return Err(String::from("JSON export holds one snapshot, use ndjson or csv for more"));
+        }
+        ExportFormat::JSON => Some(1),
+        _ => iterations,
+    };
+    let mut writer = SnapshotWriter::create(path, format)?;
+    let mut iteration = 0;
+    while iterations.is_none_or(|iterations| iteration < iterations) {
+        let snapshot = app.sampler.snapshots.recv().expect("sampler thread stopped");
+        iteration += 1;
+        writer.write(&snapshot, app)?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Snapshot with one of each record, GPU usage is unknown
+    pub(crate) fn get_snapshot(timestamp_ms: u64) -> Snapshot {
+        let counts = StateCounts { total: 2, running: 1, sleeping: 1, stopped: 0, zombie: 0 };
+        return Snapshot {
+            timestamp_ms,
+            processes: vec![ProcessSnapshot {
+                pid: Pid::from_u32(4242),
+                name: String::from("java"),
+                cpu_usage: 12.5,
+                memory: 600_000_000,
+                virtual_memory: 2_000_000_000,
+                run_time: 3600,
+                start_time: 1_700_000_000,
+                user: String::from("root"),
+                status: String::from("Sleeping"),
+                parent: Some(Pid::from_u32(1)),
+                threads: 12,
+                read_bytes: 0,
+                written_bytes: 4096,
+                command: String::from("java -jar \"my app.jar\""),
+                executable: String::from("/usr/bin/java"),
+                nice: Some(-5),
+            }],
+            global_cpu_usage: 30.25,
+            cpus: vec![CpuSnapshot { usage: 30.25, frequency: 2400 }],
+            used_memory: 4_000_000_000,
+            total_memory: 16_000_000_000,
+            used_swap: 0,
+            total_swap: 2_000_000_000,
+            gpus: Some(
+                vec![GpuSnapshot { name: String::from("amd"), usage: None, temperature: Some(48) }]
+            ),
+            disks: vec![DiskSnapshot {
+                name: String::from("/dev/sda1"),
+                mount_point: String::from("/"),
+                file_system: String::from("ext4"),
+                total_space: 500_000_000_000,
+                available_space: 120_000_000_000,
+                total_read_bytes: Some(1024),
+                total_written_bytes: None,
+            }],
+            networks: vec![NetworkSnapshot {
+                name: String::from("eth0"),
+                total_received: 1000,
+                total_transmitted: 2000,
+                total_packets_received: 10,
+                total_packets_transmitted: 20,
+                total_errors_received: 0,
+                total_errors_transmitted: 1,
+            }],
+            sensors: vec![SensorSnapshot {
+                label: String::from("coretemp Package id 0"),
+                temperature: 55.0,
+                max: 70.0,
+                critical: Some(100.0),
+            }],
+            host: HostSnapshot {
+                host_name: String::from("build"),
+                kernel_version: String::from("6.1.0"),
+                os_version: String::from("12"),
+                boot_time: 1_699_990_000,
+                uptime: 10_000,
+                load_average: [0.5, 0.25, 0.125],
+            },
+            process_counts: counts,
+            thread_counts: counts,
+        };
+    }
+
+    fn to_json(snapshot: &Snapshot) -> String {
+        return serde_json::to_string(&export_unfiltered_snapshot(snapshot)).unwrap();
+    }
+
+    fn from_json(json: &str) -> Result<Snapshot, String> {
+        let exported: ExportedSnapshot = serde_json
+            ::from_str(json)
+            .map_err(|error| error.to_string())?;
+        return exported.import();
+    }
+
+    #[test]
+    fn snapshot_round_trips() {
+        let json = to_json(&get_snapshot(1_700_000_000_123));
+        let imported = from_json(json.as_str()).unwrap();
+        assert_eq!(imported.timestamp_ms, 1_700_000_000_123);
+        assert_eq!(imported.processes[0].name, "java");
+        assert_eq!(imported.processes[0].parent, Some(Pid::from_u32(1)));
+        assert_eq!(to_json(&imported), json);
+    }
+
+    #[test]
+    fn other_schema_versions_are_rejected() {
+        let json = to_json(&get_snapshot(0));
+        let current = format!("\"schema_version\":{SCHEMA_VERSION}");
+        assert!(json.contains(current.as_str()));
+        for version in [SCHEMA_VERSION + 1, 0] {
+            let changed = json.replace(current.as_str(), &format!("\"schema_version\":{version}"));
+            let error = from_json(changed.as_str()).err().unwrap();
+            assert_eq!(error, format!("Unsupported schema version {version}, expected 1"));
+        }
+        // Fields are required, there are no older layouts to fill in
+        assert!(from_json("{\"schema_version\":1}").is_err());
+    }
+
+    #[test]
+    fn unknown_gpu_usage_is_null() {
+        let json = to_json(&get_snapshot(0));
+        let gpus = "\"gpus\":[{\"name\":\"amd\",\"usage\":null,\"temperature\":48}]";
+        assert!(json.contains(gpus));
+        let gpus = from_json(json.as_str()).unwrap().gpus.unwrap();
+        assert_eq!(gpus[0].usage, None);
+        assert_eq!(gpus[0].temperature, Some(48));
+
+        let mut snapshot = get_snapshot(0);
+        snapshot.gpus = None;
+        let json = to_json(&snapshot);
+        assert!(json.contains("\"gpus\":null"));
+        assert!(from_json(json.as_str()).unwrap().gpus.is_none());
+    }
+}
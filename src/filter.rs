@@ -0,0 +1,593 @@
+use regex::{ Regex, RegexBuilder };
+use crate::{
+    format::UnitMode,
+    process_columns::{ find_column, SortKey, PROCESS_COLUMNS },
+    snapshot::ProcessSnapshot,
+};
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Clone, Copy)]
+pub enum Operator {
+    EQ,
+    NE,
+    GT,
+    GE,
+    LT,
+    LE,
+    /// Regular expression match
+    MATCH,
+    /// Substring match
+    CONTAINS,
+}
+
+impl Operator {
+    fn from_symbol(symbol: &str) -> Option<Operator> {
+        return match symbol {
+            "=" | "==" => Some(Operator::EQ),
+            "!=" => Some(Operator::NE),
+            ">" => Some(Operator::GT),
+            ">=" => Some(Operator::GE),
+            "<" => Some(Operator::LT),
+            "<=" => Some(Operator::LE),
+            "~" => Some(Operator::MATCH),
+            ":" => Some(Operator::CONTAINS),
+            _ => None,
+        };
+    }
+
+    fn is_ordering(&self) -> bool {
+        return matches!(self, Operator::GT | Operator::GE | Operator::LT | Operator::LE);
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone)]
+pub enum Filter {
+    AND(Box<Filter>, Box<Filter>),
+    OR(Box<Filter>, Box<Filter>),
+    NOT(Box<Filter>),
+    /// Compares field of the column in PROCESS_COLUMNS against a value
+    FIELD {
+        column: usize,
+        operator: Operator,
+        text: String,
+        number: Option<f64>,
+        pattern: Regex,
+    },
+    /// Plain text matched against the selected column
+    TEXT(Regex),
+}
+
+impl Filter {
+    /// Plain text terms are matched against text_column, an index in PROCESS_COLUMNS.
+    /// Text is matched against the cell value as printed with the unit mode.
+    pub fn matches(&self, process: &ProcessSnapshot, text_column: usize, units: UnitMode) -> bool {
+        return match self {
+            Filter::AND(a, b) => {
+                a.matches(process, text_column, units) && b.matches(process, text_column, units)
+            }
+            Filter::OR(a, b) => {
+                a.matches(process, text_column, units) || b.matches(process, text_column, units)
+            }
+            Filter::NOT(filter) => !filter.matches(process, text_column, units),
+            Filter::FIELD { column, operator, text, number, pattern } => {
+                let column = &PROCESS_COLUMNS[*column];
+                let filter_text = (column.value)(process, units);
+                match operator {
+                    Operator::MATCH | Operator::CONTAINS => pattern.is_match(filter_text.as_str()),
+                    Operator::EQ | Operator::NE => {
+                        let is_equal = match ((column.sort_key)(process), number) {
+                            (SortKey::NUMBER(value), Some(number)) => value == *number,
+                            _ => pattern.is_match(filter_text.as_str()),
+                        };
+                        is_equal == (*operator == Operator::EQ)
+                    }
+                    _ => {
+                        let ordering = match ((column.sort_key)(process), number) {
+                            (SortKey::NUMBER(value), Some(number)) => value.partial_cmp(number),
+                            (SortKey::TEXT(value), _) => Some(value.as_str().cmp(text.as_str())),
+                            _ => None,
+                        };
+                        ordering.is_some_and(|ordering| {
+                            return match operator {
+                                Operator::GT => ordering.is_gt(),
+                                Operator::GE => ordering.is_ge(),
+                                Operator::LT => ordering.is_lt(),
+                                _ => ordering.is_le(),
+                            };
+                        })
+                    }
+                }
+            }
+            Filter::TEXT(pattern) => {
+                let column = &PROCESS_COLUMNS[text_column];
+                pattern.is_match((column.value)(process, units).as_str())
+            }
+        };
+    }
+
+    /// Returns patterns whose matches are highlighted in cells of the column in PROCESS_COLUMNS
+    pub fn get_highlights(&self, column: usize, text_column: usize) -> Vec<&Regex> {
+        let mut highlights = Vec::new();
+        self.collect_highlights(column, text_column, &mut highlights);
+        return highlights;
+    }
+
+    fn collect_highlights<'a>(
+        &'a self,
+        column: usize,
+        text_column: usize,
+        highlights: &mut Vec<&'a Regex>
+    ) {
+        match self {
+            Filter::AND(a, b) | Filter::OR(a, b) => {
+                a.collect_highlights(column, text_column, highlights);
+                b.collect_highlights(column, text_column, highlights);
+            }
+            // Negated terms never match the printed text
+            Filter::NOT(_) => (),
+            Filter::FIELD { column: field_column, operator, pattern, .. } => {
+                if
+                    *field_column == column &&
+                    matches!(operator, Operator::MATCH | Operator::CONTAINS | Operator::EQ)
+                {
+                    highlights.push(pattern);
+                }
+            }
+            Filter::TEXT(pattern) => {
+                if text_column == column {
+                    highlights.push(pattern);
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq)]
+enum Token {
+    OPEN,
+    CLOSE,
+    OPERATOR(String),
+    WORD(String),
+    /// Quoted text, never treated as a keyword or field
+    QUOTED(String),
+}
+
+const OPERATOR_CHARACTERS: [char; 6] = ['=', '!', '~', ':', '<', '>'];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::OPEN);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::CLOSE);
+        } else if OPERATOR_CHARACTERS.contains(&c) {
+            let mut operator = String::new();
+            while let Some(&c) = chars.peek().filter(|c| OPERATOR_CHARACTERS.contains(c)) {
+                operator.push(c);
+                chars.next();
+            }
+            tokens.push(Token::OPERATOR(operator));
+        } else if c == '"' {
+            chars.next();
+            let mut quoted = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => {
+                        break;
+                    }
+                    Some('\\') => {
+                        if let Some(escaped) = chars.next() {
+                            quoted.push(escaped);
+                        }
+                    }
+                    Some(c) => quoted.push(c),
+                    None => {
+                        return Err(String::from("Unclosed quote"));
+                    }
+                }
+            }
+            tokens.push(Token::QUOTED(quoted));
+        } else {
+            let mut word = String::new();
+            while
+                let Some(&c) = chars
+                    .peek()
+                    .filter(|c| {
+                        !c.is_whitespace() &&
+                            !OPERATOR_CHARACTERS.contains(c) &&
+                            !['(', ')', '"'].contains(c)
+                    })
+            {
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::WORD(word));
+        }
+    }
+    return Ok(tokens);
+}
+
+fn is_keyword(token: Option<&Token>, keyword: &str) -> bool {
+    return match token {
+        Some(Token::WORD(word)) => word.eq_ignore_ascii_case(keyword),
+        _ => false,
+    };
+}
+
+/// Parses number with an optional unit, e.g. "500M" or "1.5GiB".
+/// K, M, G and T are powers of 1000, Ki, Mi, Gi and Ti powers of 1024, a trailing B is ignored.
+fn parse_number(text: &str) -> Option<f64> {
+    let upper = text.to_uppercase();
+    let without_bytes = upper.strip_suffix('B').unwrap_or(upper.as_str());
+    let (number, base) = match without_bytes.strip_suffix('I') {
+        Some(number) => (number, 1024_f64),
+        None => (without_bytes, 1000_f64),
+    };
+    for (index, prefix) in ['K', 'M', 'G', 'T'].iter().enumerate() {
+        if let Some(number) = number.strip_suffix(*prefix) {
+            let multiplier = base.powi((index as i32) + 1);
+            return number.parse::<f64>().ok().map(|number| number * multiplier);
+        }
+    }
+    if base == 1024_f64 {
+        return None;
+    }
+    return number.parse().ok();
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    case_insensitive: bool,
+    literal: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        return self.tokens.get(self.position);
+    }
+
+    fn compile(&self, text: &str, literal: bool) -> Result<Regex, String> {
+        return compile_pattern(text, self.case_insensitive, literal || self.literal);
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.parse_and()?;
+        while is_keyword(self.peek(), "or") {
+            self.position += 1;
+            filter = Filter::OR(Box::new(filter), Box::new(self.parse_and()?));
+        }
+        return Ok(filter);
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.parse_not()?;
+        while is_keyword(self.peek(), "and") {
+            self.position += 1;
+            filter = Filter::AND(Box::new(filter), Box::new(self.parse_not()?));
+        }
+        return Ok(filter);
+    }
+
+    fn parse_not(&mut self) -> Result<Filter, String> {
+        if is_keyword(self.peek(), "not") {
+            self.position += 1;
+            return Ok(Filter::NOT(Box::new(self.parse_not()?)));
+        }
+        return self.parse_term();
+    }
+
+    fn parse_term(&mut self) -> Result<Filter, String> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        return match token {
+            Some(Token::OPEN) => {
+                let filter = self.parse_or()?;
+                if self.peek() != Some(&Token::CLOSE) {
+                    return Err(String::from("Expected ')'"));
+                }
+                self.position += 1;
+                Ok(filter)
+            }
+            Some(Token::WORD(field)) if matches!(self.peek(), Some(Token::OPERATOR(_))) => {
+                let field = field.to_string();
+                self.parse_field(field.as_str())
+            }
+            Some(Token::WORD(text)) => Ok(Filter::TEXT(self.compile(text, false)?)),
+            Some(Token::QUOTED(text)) => Ok(Filter::TEXT(self.compile(text, true)?)),
+            Some(Token::CLOSE) => Err(String::from("Unexpected ')'")),
+            Some(Token::OPERATOR(operator)) => Err(format!("Expected field before '{operator}'")),
+            None => Err(String::from("Unexpected end of filter")),
+        };
+    }
+
+    fn parse_field(&mut self, field: &str) -> Result<Filter, String> {
+        let Some(column) = find_column(field) else {
+            return Err(format!("Unknown field '{field}'"));
+        };
+        let Some(Token::OPERATOR(symbol)) = self.tokens.get(self.position) else {
+            return Err(format!("Expected operator after '{field}'"));
+        };
+        let symbol = symbol.to_string();
+        let Some(operator) = Operator::from_symbol(symbol.as_str()) else {
+            return Err(format!("Unknown operator '{symbol}'"));
+        };
+        self.position += 1;
+        let (text, quoted) = match self.tokens.get(self.position) {
+            Some(Token::WORD(text)) => (text.to_string(), false),
+            Some(Token::QUOTED(text)) => (text.to_string(), true),
+            _ => {
+                return Err(format!("Expected value after '{symbol}'"));
+            }
+        };
+        self.position += 1;
+        let number = parse_number(text.as_str());
+        if operator.is_ordering() && number.is_none() {
+            return Err(format!("Expected number after '{symbol}'"));
+        }
+        let pattern = match operator {
+            Operator::MATCH => self.compile(text.as_str(), quoted)?,
+            // Equality and substring matches never interpret the value as a regular expression
+            Operator::CONTAINS => self.compile(text.as_str(), true)?,
+            _ => self.compile(format!("^{}$", regex::escape(text.as_str())).as_str(), false)?,
+        };
+        return Ok(Filter::FIELD { column, operator, text, number, pattern });
+    }
+}
+
+fn compile_pattern(text: &str, case_insensitive: bool, literal: bool) -> Result<Regex, String> {
+    let pattern = if literal { regex::escape(text) } else { text.to_string() };
+    return RegexBuilder::new(pattern.as_str())
+        .case_insensitive(case_insensitive)
+        .build()
+        .map_err(|error| {
+            // Errors are reduced to their last line so they fit on the input row
+            let message = error.to_string();
+            let last_line = message.lines().last().unwrap_or_default().trim();
+            return last_line.trim_start_matches("error: ").to_string();
+        });
+}
+
+/// Returns true when text uses fields, keywords or quotes, so parse errors should be reported
+/// instead of falling back to matching the whole text against the selected column
+fn is_query(tokens: &[Token]) -> bool {
+    return tokens.iter().enumerate().any(|(i, token)| {
+        return match token {
+            Token::QUOTED(_) => true,
+            Token::WORD(word) => {
+                ["and", "or", "not"].iter().any(|keyword| word.eq_ignore_ascii_case(keyword)) ||
+                    (find_column(word).is_some() &&
+                        matches!(tokens.get(i + 1), Some(Token::OPERATOR(_))))
+            }
+            _ => false,
+        };
+    });
+}
+
+/// Parses search text into a filter, returns None for empty text.
+/// Text without fields or keywords is matched as a whole against the selected column.
+pub fn parse_filter(
+    text: &str,
+    case_insensitive: bool,
+    literal: bool
+) -> Result<Option<Filter>, String> {
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    let plain_text = || {
+        return compile_pattern(text, case_insensitive, literal).map(|re| Some(Filter::TEXT(re)));
+    };
+    let tokens = match tokenize(text) {
+        Ok(tokens) => tokens,
+        Err(_) if !text.contains('"') => {
+            return plain_text();
+        }
+        Err(error) => {
+            return Err(error);
+        }
+    };
+    if !is_query(&tokens) {
+        return plain_text();
+    }
+    let mut parser = Parser { tokens, position: 0, case_insensitive, literal };
+    let filter = parser.parse_or()?;
+    if parser.position < parser.tokens.len() {
+        return Err(String::from("Expected 'and', 'or' or end of filter"));
+    }
+    return Ok(Some(filter));
+}
+
+#[cfg(test)]
+mod tests {
+    use sysinfo::Pid;
+
+    use super::*;
+
+    /// Writes the parsed tree with explicit parentheses, fields as id, operator and value
+    fn describe(filter: &Filter) -> String {
+        return match filter {
+            Filter::AND(a, b) => format!("({} and {})", describe(a), describe(b)),
+            Filter::OR(a, b) => format!("({} or {})", describe(a), describe(b)),
+            Filter::NOT(filter) => format!("not {}", describe(filter)),
+            Filter::FIELD { column, operator, text, .. } => {
+                let symbol = match operator {
+                    Operator::EQ => "=",
+                    Operator::NE => "!=",
+                    Operator::GT => ">",
+                    Operator::GE => ">=",
+                    Operator::LT => "<",
+                    Operator::LE => "<=",
+                    Operator::MATCH => "~",
+                    Operator::CONTAINS => ":",
+                };
+                format!("{}{}{}", PROCESS_COLUMNS[*column].id, symbol, text)
+            }
+            Filter::TEXT(pattern) => format!("/{}/", pattern.as_str()),
+        };
+    }
+
+    fn parse(text: &str) -> String {
+        return match parse_filter(text, false, false) {
+            Ok(Some(filter)) => describe(&filter),
+            Ok(None) => String::from("none"),
+            Err(error) => format!("error: {error}"),
+        };
+    }
+
+    fn get_process() -> ProcessSnapshot {
+        return ProcessSnapshot {
+            pid: Pid::from_u32(4242),
+            name: String::from("java"),
+            cpu_usage: 25.0,
+            memory: 600_000_000,
+            virtual_memory: 2_000_000_000,
+            run_time: 3600,
+            start_time: 1_700_000_000,
+            user: String::from("root"),
+            status: String::from("Sleeping"),
+            parent: Some(Pid::from_u32(1)),
+            threads: 12,
+            read_bytes: 0,
+            written_bytes: 4096,
+            command: String::from("java -jar \"my app.jar\""),
+            executable: String::from("/usr/bin/java"),
+            nice: Some(0),
+        };
+    }
+
+    /// Plain text terms are matched against the name column
+    fn matches(text: &str) -> bool {
+        let filter = parse_filter(text, false, false).unwrap().unwrap();
+        return filter.matches(&get_process(), find_column("name").unwrap(), UnitMode::IEC);
+    }
+
+    fn matches_with_units(text: &str, units: UnitMode) -> bool {
+        let filter = parse_filter(text, false, false).unwrap().unwrap();
+        return filter.matches(&get_process(), find_column("name").unwrap(), units);
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(parse("name ~ a or name ~ b and cpu > 1"), "(name~a or (name~b and cpu>1))");
+        assert_eq!(parse("name ~ a and name ~ b or cpu > 1"), "((name~a and name~b) or cpu>1)");
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        assert_eq!(parse("not name ~ a and cpu > 1"), "(not name~a and cpu>1)");
+        assert_eq!(parse("not not user = root"), "not not user=root");
+    }
+
+    #[test]
+    fn parentheses_group_terms() {
+        assert_eq!(parse("(name ~ a or name ~ b) and cpu > 1"), "((name~a or name~b) and cpu>1)");
+        assert_eq!(parse("not (user = root or ((cpu < 5)))"), "not (user=root or cpu<5)");
+    }
+
+    #[test]
+    fn keywords_ignore_case() {
+        assert_eq!(
+            parse("name ~ a AND NOT cpu > 1 Or user = x"),
+            "((name~a and not cpu>1) or user=x)"
+        );
+    }
+
+    #[test]
+    fn quoted_strings_keep_spaces_and_escapes() {
+        assert_eq!(parse("cmd : \"my app.jar\""), "cmd:my app.jar");
+        assert_eq!(parse("cmd = \"say \\\"hi\\\"\""), "cmd=say \"hi\"");
+        // Quoted terms are matched literally
+        assert_eq!(parse("\"a.b\" and user = root"), "(/a\\.b/ and user=root)");
+        assert!(matches("cmd : \"my app\""));
+    }
+
+    #[test]
+    fn comparison_operators() {
+        assert!(matches("cpu > 20"));
+        assert!(!matches("cpu > 25"));
+        assert!(matches("cpu >= 25"));
+        assert!(!matches("cpu < 25"));
+        assert!(matches("cpu <= 25"));
+        assert!(matches("cpu = 25"));
+        assert!(matches("cpu == 25"));
+        assert!(!matches("cpu != 25"));
+        assert!(matches("mem >= 500M and mem < 1G"));
+        assert!(matches("vmem > 1.5g"));
+        assert!(matches("name = java and name != jav"));
+        assert!(matches("name ~ ^ja.a$"));
+        assert!(matches("exe : bin/ja"));
+        assert!(!matches("exe : bin.ja"));
+        assert!(matches("not user = nobody"));
+    }
+
+    #[test]
+    fn malformed_queries_are_errors() {
+        assert_eq!(parse("cpu > abc"), "error: Expected number after '>'");
+        assert_eq!(parse("(name ~ a or cpu > 1"), "error: Expected ')'");
+        assert_eq!(parse("name ~ a)"), "error: Expected 'and', 'or' or end of filter");
+        assert_eq!(parse("name ~ a and"), "error: Unexpected end of filter");
+        assert_eq!(parse("name ~ a and ) "), "error: Unexpected ')'");
+        assert_eq!(parse("name ~"), "error: Expected value after '~'");
+        assert_eq!(parse("name <> x"), "error: Unknown operator '<>'");
+        assert_eq!(parse("size > 1 and name ~ a"), "error: Unknown field 'size'");
+        assert_eq!(parse("name ~ a and > 1"), "error: Expected field before '>'");
+        assert_eq!(parse("name ~ \"java"), "error: Unclosed quote");
+        assert!(parse("name ~ [a").starts_with("error: "));
+    }
+
+    #[test]
+    fn plain_text_matches_selected_column() {
+        assert_eq!(parse(""), "none");
+        assert_eq!(parse("   "), "none");
+        assert_eq!(parse("java"), "/java/");
+        assert_eq!(parse("foo bar"), "/foo bar/");
+        // Operators are plain text unless they follow a column id
+        assert_eq!(parse("a:b size>1"), "/a:b size>1/");
+        // Apostrophes are not quotes
+        assert_eq!(parse("it's"), "/it's/");
+        assert!(matches("av"));
+        assert!(!matches("JAVA"));
+        let filter = parse_filter("JAVA", true, false).unwrap().unwrap();
+        assert!(filter.matches(&get_process(), find_column("name").unwrap(), UnitMode::IEC));
+        let filter = parse_filter("j.va", false, true).unwrap().unwrap();
+        assert!(!filter.matches(&get_process(), find_column("name").unwrap(), UnitMode::IEC));
+    }
+
+    #[test]
+    fn number_units() {
+        assert_eq!(parse_number("500"), Some(500.0));
+        assert_eq!(parse_number("500M"), Some(500e6));
+        assert_eq!(parse_number("2kb"), Some(2000.0));
+        assert_eq!(parse_number("1.5G"), Some(1.5e9));
+        assert_eq!(parse_number("1T"), Some(1e12));
+        assert_eq!(parse_number("4Ki"), Some(4096.0));
+        assert_eq!(parse_number("4KiB"), Some(4096.0));
+        assert_eq!(parse_number("1.5gi"), Some(1.5 * 1024_f64.powi(3)));
+        assert_eq!(parse_number("2Mi"), Some(2.0 * 1024_f64.powi(2)));
+        assert_eq!(parse_number("5i"), None);
+        assert_eq!(parse_number("MiB"), None);
+        assert_eq!(parse_number("5X"), None);
+        assert!(matches("mem > 572Mi and mem < 573MiB"));
+        assert!(!matches("vmem > 2Gi"));
+    }
+
+    #[test]
+    fn text_matches_printed_value() {
+        // 600000000 bytes are printed as 572.2 MiB or 600.0 MB
+        assert!(matches_with_units("mem : MiB", UnitMode::IEC));
+        assert!(!matches_with_units("mem : MiB", UnitMode::SI));
+        assert!(matches_with_units("mem = \"600.0 MB\"", UnitMode::SI));
+        assert!(matches("cpu : %"));
+        assert!(matches("cpu ~ ^25\\.0"));
+        assert!(matches("time = \"01:00:00\""));
+        assert!(!matches("time : 3600"));
+    }
+}
@@ -0,0 +1,133 @@
+/// Prefixes of byte sizes, scaled to the largest unit that keeps the value at least 1
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Clone, Copy)]
+pub enum UnitMode {
+    /// Powers of 1024: KiB, MiB, GiB
+    IEC,
+    /// Powers of 1000: KB, MB, GB
+    SI,
+}
+
+impl UnitMode {
+    pub fn from_name(name: &str) -> Option<UnitMode> {
+        return match name.to_lowercase().as_str() {
+            "iec" => Some(UnitMode::IEC),
+            "si" => Some(UnitMode::SI),
+            _ => None,
+        };
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            UnitMode::IEC => "iec",
+            UnitMode::SI => "si",
+        };
+    }
+}
+
+const IEC_UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+const SI_UNITS: [&str; 6] = ["B", "KB", "MB", "GB", "TB", "PB"];
+
+/// Decimals of scaled byte sizes and percentages
+const PRECISION: usize = 1;
+
+fn scale_bytes(bytes: f64, mode: UnitMode) -> String {
+    let (base, units) = match mode {
+        UnitMode::IEC => (1024.0, IEC_UNITS),
+        UnitMode::SI => (1000.0, SI_UNITS),
+    };
+    let mut value = bytes;
+    let mut unit = 0;
+    // Values that would round up to the base are shown in the next unit, e.g. 1.0 MiB for 1023.97 KiB
+    let rounding = 0.5 / (10_f64).powi(PRECISION as i32);
+    while unit < units.len() - 1 && value >= base - rounding {
+        value /= base;
+        unit += 1;
+    }
+    if unit == 0 {
+        return format!("{:.0} {}", value, units[unit]);
+    }
+    return format!("{:.*} {}", PRECISION, value, units[unit]);
+}
+
+/// Formats a byte count, e.g. "1.5 GiB", bytes below the first unit are shown without decimals
+pub fn format_bytes(bytes: u64, mode: UnitMode) -> String {
+    return scale_bytes(bytes as f64, mode);
+}
+
+/// Formats bytes per second, e.g. "12.0 KiB/s"
+pub fn format_rate(bytes_per_second: f64, mode: UnitMode) -> String {
+    return format!("{}/s", scale_bytes(bytes_per_second, mode));
+}
+
+/// Formats a percentage with fixed precision, e.g. "3.2%"
+pub fn format_percent(percent: f64) -> String {
+    return format!("{:.*}%", PRECISION, percent);
+}
+
+/// Formats seconds as days and time, e.g. "1d 02:03:04", days are left out below one day
+pub fn format_elapsed(seconds: u64) -> String {
+    let time = format!("{:02}:{:02}:{:02}", (seconds % 86400) / 3600, (seconds % 3600) / 60, seconds % 60);
+    if seconds < 86400 {
+        return time;
+    }
+    return format!("{}d {}", seconds / 86400, time);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_mode_names() {
+        assert!(UnitMode::from_name("SI") == Some(UnitMode::SI));
+        assert!(UnitMode::from_name("iec") == Some(UnitMode::IEC));
+        assert!(UnitMode::from_name("binary").is_none());
+        assert_eq!(UnitMode::IEC.name(), "iec");
+    }
+
+    #[test]
+    fn bytes_below_first_unit_have_no_decimals() {
+        assert_eq!(format_bytes(0, UnitMode::IEC), "0 B");
+        assert_eq!(format_bytes(1000, UnitMode::IEC), "1000 B");
+        assert_eq!(format_bytes(999, UnitMode::SI), "999 B");
+    }
+
+    #[test]
+    fn bytes_scale_by_unit_mode() {
+        assert_eq!(format_bytes(1536, UnitMode::IEC), "1.5 KiB");
+        assert_eq!(format_bytes(1536, UnitMode::SI), "1.5 KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024, UnitMode::IEC), "3.0 GiB");
+        assert_eq!(format_bytes(2_000_398_934_016, UnitMode::SI), "2.0 TB");
+        assert_eq!(format_bytes(u64::MAX, UnitMode::IEC), "16384.0 PiB");
+    }
+
+    #[test]
+    fn bytes_rounding_to_base_move_to_next_unit() {
+        assert_eq!(format_bytes(1024 * 1024 - 1, UnitMode::IEC), "1.0 MiB");
+        assert_eq!(format_bytes(999_960, UnitMode::SI), "1.0 MB");
+        assert_eq!(format_bytes(999_940, UnitMode::SI), "999.9 KB");
+    }
+
+    #[test]
+    fn rates_are_per_second() {
+        assert_eq!(format_rate(0.0, UnitMode::IEC), "0 B/s");
+        assert_eq!(format_rate(12_288.0, UnitMode::IEC), "12.0 KiB/s");
+        assert_eq!(format_rate(1.5e9, UnitMode::SI), "1.5 GB/s");
+    }
+
+    #[test]
+    fn percent_has_fixed_precision() {
+        assert_eq!(format_percent(0.0), "0.0%");
+        assert_eq!(format_percent(42.06), "42.1%");
+        assert_eq!(format_percent(100.0), "100.0%");
+    }
+
+    #[test]
+    fn elapsed_shows_days_from_one_day() {
+        assert_eq!(format_elapsed(0), "00:00:00");
+        assert_eq!(format_elapsed(3723), "01:02:03");
+        assert_eq!(format_elapsed(86_399), "23:59:59");
+        assert_eq!(format_elapsed(93_784), "1d 02:03:04");
+    }
+}
@@ -0,0 +1,322 @@
+use std::{ fs, panic::AssertUnwindSafe, path::{ Path, PathBuf } };
+
+use machine_info::Machine;
+
+use crate::{ snapshot::GpuSnapshot, terminal::catch_quiet_panic };
+
+/// Source of the usage and temperature of the graphics cards
+pub trait GpuProvider: Send {
+    /// Status of every card found, None when the provider cannot be read at all
+    fn read(&mut self) -> Option<Vec<GpuSnapshot>>;
+}
+
+/// Reads the DRM cards and their hwmon sensors below a sysfs root,
+/// which is "/sys" unless pointed at a copy of its layout
+pub struct SysfsGpuProvider {
+    root: PathBuf,
+}
+
+impl SysfsGpuProvider {
+    pub fn new(root: PathBuf) -> SysfsGpuProvider {
+        return SysfsGpuProvider { root };
+    }
+}
+
+impl GpuProvider for SysfsGpuProvider {
+    fn read(&mut self) -> Option<Vec<GpuSnapshot>> {
+        let entries = fs::read_dir(self.root.join("class").join("drm")).ok()?;
+        // Connectors such as "card0-HDMI-A-1" and render nodes are skipped
+        let mut cards: Vec<(u32, PathBuf)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let index = entry.file_name().to_str()?.strip_prefix("card")?.parse().ok()?;
+                return Some((index, entry.path().join("device")));
+            })
+            .collect();
+        cards.sort_by_key(|(index, _)| *index);
+        return Some(cards.iter().map(|(_, device)| read_drm_device(device)).collect());
+    }
+}
+
+fn read_sysfs_value(path: &Path) -> Option<String> {
+    return fs::read_to_string(path).ok().map(|value| value.trim().to_string());
+}
+
+fn get_vendor_name(vendor_id: &str) -> String {
+    return (
+        match vendor_id {
+            "0x1002" => "AMD",
+            "0x10de" => "NVIDIA",
+            "0x8086" => "Intel",
+            _ => vendor_id,
+        }
+    ).to_string();
+}
+
+/// Busy percent is only reported by some drivers, e.g. amdgpu
+fn read_drm_device(device: &Path) -> GpuSnapshot {
+    return GpuSnapshot {
+        name: read_sysfs_value(&device.join("vendor"))
+            .map(|vendor_id| get_vendor_name(&vendor_id))
+            .unwrap_or_default(),
+        usage: read_sysfs_value(&device.join("gpu_busy_percent")).and_then(|usage| {
+            return usage.parse().ok();
+        }),
+        temperature: read_hwmon_temperature(&device.join("hwmon")),
+    };
+}
+
+/// First temperature of the lowest numbered hwmon device, converted from millidegrees
+fn read_hwmon_temperature(hwmon: &Path) -> Option<u32> {
+    let mut sensors: Vec<PathBuf> = fs::read_dir(hwmon)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    sensors.sort();
+    return sensors.iter().find_map(|sensor| {
+        let millidegrees: i64 = read_sysfs_value(&sensor.join("temp1_input"))?.parse().ok()?;
+        return Some((millidegrees.max(0) / 1000) as u32);
+    });
+}
+
+/// Reads NVIDIA cards through NVML. NVML is initialized once, and after a failure or a read
+/// without cards it is not tried again, so hosts without it do not pay for it every sample.
+#[derive(Default)]
+pub struct MachineInfoGpuProvider {
+    /// Created on the first read
+    machine: Option<Machine>,
+    unavailable: bool,
+}
+
+impl GpuProvider for MachineInfoGpuProvider {
+    fn read(&mut self) -> Option<Vec<GpuSnapshot>> {
+        if self.unavailable {
+            return None;
+        }
+        let machine = &mut self.machine;
+        // NOTE: machine_info seems to be rather unstable, throwing sometimes on access
+        let gpus: Option<Vec<GpuSnapshot>> = catch_quiet_panic(
+            AssertUnwindSafe(|| {
+                return machine
+                    .get_or_insert_with(Machine::new)
+                    .graphics_status()
+                    .iter()
+                    .map(|graphics| GpuSnapshot {
+                        name: String::from("NVIDIA"),
+                        usage: Some(graphics.gpu),
+                        temperature: Some(graphics.temperature),
+                    })
+                    .collect();
+            })
+        );
+        if gpus.as_ref().is_none_or(|gpus| gpus.is_empty()) {
+            self.machine = None;
+            self.unavailable = true;
+            return None;
+        }
+        return gpus;
+    }
+}
+
+/// Reads the GPUs of the providers in order. The first provider listing a card decides its place,
+/// later providers fill in the values it cannot read, e.g. NVML for NVIDIA cards found in sysfs.
+/// Cards found before stay listed without values while no provider can read them,
+/// so the header layout does not jump.
+pub struct GpuReader {
+    providers: Vec<Box<dyn GpuProvider>>,
+    /// Names of the cards of the last successful read
+    known: Vec<String>,
+}
+
+impl GpuReader {
+    /// Reads sysfs below the root, falling back to machine_info
+    pub fn new(sysfs_root: PathBuf) -> GpuReader {
+        return GpuReader::with_providers(
+            vec![
+                Box::new(SysfsGpuProvider::new(sysfs_root)),
+                Box::new(MachineInfoGpuProvider::default())
+            ]
+        );
+    }
+
+    pub fn with_providers(providers: Vec<Box<dyn GpuProvider>>) -> GpuReader {
+        return GpuReader { providers, known: Vec::new() };
+    }
+
+    /// None when no cards have been found yet. Later providers are only read while a card
+    /// lacks a value or no card was found.
+    pub fn read(&mut self) -> Option<Vec<GpuSnapshot>> {
+        let mut gpus: Vec<GpuSnapshot> = Vec::new();
+        for provider in self.providers.iter_mut() {
+            let complete = gpus.iter().all(|gpu| gpu.usage.is_some() && gpu.temperature.is_some());
+            if !gpus.is_empty() && complete {
+                break;
+            }
+            if let Some(cards) = provider.read() {
+                merge_gpus(&mut gpus, cards);
+            }
+        }
+        if !gpus.is_empty() {
+            self.known = gpus.iter().map(|gpu| gpu.name.to_string()).collect();
+            return Some(gpus);
+        }
+        if self.known.is_empty() {
+            return None;
+        }
+        return Some(
+            self.known
+                .iter()
+                .map(|name| GpuSnapshot { name: name.to_string(), usage: None, temperature: None })
+                .collect()
+        );
+    }
+}
+
+/// Fills the missing values of the cards from cards with the same name, matched in order.
+/// Cards without a match are added.
+fn merge_gpus(gpus: &mut Vec<GpuSnapshot>, cards: Vec<GpuSnapshot>) {
+    let mut matched = vec![false; gpus.len()];
+    for card in cards {
+        let position = gpus
+            .iter()
+            .zip(&matched)
+            .position(|(gpu, matched)| !matched && gpu.name == card.name);
+        match position {
+            Some(position) => {
+                matched[position] = true;
+                let gpu = &mut gpus[position];
+                gpu.usage = gpu.usage.or(card.usage);
+                gpu.temperature = gpu.temperature.or(card.temperature);
+            }
+            None => {
+                matched.push(true);
+                gpus.push(card);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ env, process };
+
+    use super::*;
+
+    /// Directory below the temp dir laid out like sysfs, removed when dropped
+    struct SysfsFixture {
+        root: PathBuf,
+    }
+
+    impl SysfsFixture {
+        fn new(name: &str) -> SysfsFixture {
+            let root = env::temp_dir().join(format!("task-manager-{}-{}", process::id(), name));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(root.join("class").join("drm")).unwrap();
+            return SysfsFixture { root };
+        }
+
+        fn write(&self, path: &str, contents: &str) {
+            let path = self.root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        fn reader(&self, fallback: fn() -> Option<Vec<GpuSnapshot>>) -> GpuReader {
+            return GpuReader::with_providers(
+                vec![
+                    Box::new(SysfsGpuProvider::new(self.root.clone())),
+                    Box::new(FakeProvider(fallback))
+                ]
+            );
+        }
+    }
+
+    impl Drop for SysfsFixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    /// Stands in for NVML
+    struct FakeProvider(fn() -> Option<Vec<GpuSnapshot>>);
+
+    impl GpuProvider for FakeProvider {
+        fn read(&mut self) -> Option<Vec<GpuSnapshot>> {
+            return (self.0)();
+        }
+    }
+
+    fn gpu(name: &str, usage: Option<u32>, temperature: Option<u32>) -> GpuSnapshot {
+        return GpuSnapshot { name: name.to_string(), usage, temperature };
+    }
+
+    fn describe(gpus: Option<Vec<GpuSnapshot>>) -> Vec<(String, Option<u32>, Option<u32>)> {
+        return gpus
+            .unwrap_or_default()
+            .into_iter()
+            .map(|gpu| (gpu.name, gpu.usage, gpu.temperature))
+            .collect();
+    }
+
+    fn no_cards() -> Option<Vec<GpuSnapshot>> {
+        return None;
+    }
+
+    #[test]
+    fn reads_amd_busy_percent_and_temperature() {
+        let fixture = SysfsFixture::new("amd");
+        fixture.write("class/drm/card0/device/vendor", "0x1002\n");
+        fixture.write("class/drm/card0/device/gpu_busy_percent", "42\n");
+        fixture.write("class/drm/card0/device/hwmon/hwmon3/temp1_input", "61000\n");
+        fixture.write("class/drm/card0-HDMI-A-1/status", "connected\n");
+        fixture.write("class/drm/renderD128/dev", "226:128\n");
+        assert_eq!(
+            describe(fixture.reader(no_cards).read()),
+            vec![(String::from("AMD"), Some(42), Some(61))]
+        );
+    }
+
+    #[test]
+    fn missing_files_leave_values_empty() {
+        let fixture = SysfsFixture::new("missing");
+        fixture.write("class/drm/card1/device/vendor", "0x8086\n");
+        fixture.write("class/drm/card0/device/gpu_busy_percent", "not a number\n");
+        assert_eq!(
+            describe(fixture.reader(no_cards).read()),
+            vec![(String::new(), None, None), (String::from("Intel"), None, None)]
+        );
+        assert!(SysfsGpuProvider::new(fixture.root.join("absent")).read().is_none());
+    }
+
+    #[test]
+    fn nvidia_values_fall_back_to_nvml() {
+        let fixture = SysfsFixture::new("nvidia");
+        fixture.write("class/drm/card0/device/vendor", "0x1002\n");
+        fixture.write("class/drm/card0/device/gpu_busy_percent", "7\n");
+        fixture.write("class/drm/card0/device/hwmon/hwmon0/temp1_input", "45000\n");
+        fixture.write("class/drm/card1/device/vendor", "0x10de\n");
+        let mut reader = fixture.reader(|| {
+            return Some(vec![gpu("NVIDIA", Some(30), Some(55)), gpu("NVIDIA", Some(80), Some(70))]);
+        });
+        assert_eq!(
+            describe(reader.read()),
+            vec![
+                (String::from("AMD"), Some(7), Some(45)),
+                (String::from("NVIDIA"), Some(30), Some(55)),
+                (String::from("NVIDIA"), Some(80), Some(70))
+            ]
+        );
+    }
+
+    #[test]
+    fn known_cards_stay_listed_without_values() {
+        let fixture = SysfsFixture::new("known");
+        fixture.write("class/drm/card0/device/vendor", "0x1002\n");
+        let mut reader = fixture.reader(no_cards);
+        assert_eq!(describe(reader.read()), vec![(String::from("AMD"), None, None)]);
+        fs::remove_dir_all(fixture.root.join("class/drm/card0")).unwrap();
+        assert_eq!(describe(reader.read()), vec![(String::from("AMD"), None, None)]);
+        assert!(GpuReader::with_providers(vec![Box::new(FakeProvider(no_cards))]).read().is_none());
+    }
+}
@@ -0,0 +1,102 @@
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Clone, Copy)]
+pub enum GraphStyle {
+    /// Braille dots, 2 points per character horizontally and 4 vertically
+    BRAILLE,
+    /// Block characters, 1 point per character horizontally and 8 vertically
+    BLOCK,
+}
+
+impl GraphStyle {
+    pub fn from_name(name: &str) -> Option<GraphStyle> {
+        return match name.to_lowercase().as_str() {
+            "braille" => Some(GraphStyle::BRAILLE),
+            "block" => Some(GraphStyle::BLOCK),
+            _ => None,
+        };
+    }
+
+    /// Number of points drawn per character column
+    pub fn points_per_column(&self) -> usize {
+        return match self {
+            GraphStyle::BRAILLE => 2,
+            GraphStyle::BLOCK => 1,
+        };
+    }
+}
+
+const BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Bits of the braille dots from the bottom up, for the left and right dot column
+const BRAILLE_DOTS: [[u32; 4]; 2] = [
+    [0x40, 0x04, 0x02, 0x01],
+    [0x80, 0x20, 0x10, 0x08],
+];
+
+/// Number of filled levels of a point in a graph cell, counted from the bottom of the cell
+fn get_cell_fill(value: Option<f64>, row_from_bottom: usize, levels_per_row: usize, height: usize) -> usize {
+    let Some(value) = value else {
+        return 0;
+    };
+    let total_levels = (value.clamp(0.0, 1.0) * ((height * levels_per_row) as f64)).round() as usize;
+    return total_levels.saturating_sub(row_from_bottom * levels_per_row).min(levels_per_row);
+}
+
+/// Draws values between 0 and 1 as a filled area graph of height rows, oldest value on the left.
+/// Each row holds points_per_column values per character, None leaves the point empty.
+pub fn format_graph(values: &[Option<f64>], height: usize, style: GraphStyle) -> Vec<String> {
+    let points_per_column = style.points_per_column();
+    let mut rows = Vec::with_capacity(height);
+    for row in 0..height {
+        let row_from_bottom = height - 1 - row;
+        let line: String = values
+            .chunks(points_per_column)
+            .map(|points| {
+                return match style {
+                    GraphStyle::BLOCK => {
+                        BLOCKS[get_cell_fill(points[0], row_from_bottom, 8, height)]
+                    }
+                    GraphStyle::BRAILLE => {
+                        let mut bits = 0;
+                        for (column, point) in points.iter().enumerate() {
+                            let fill = get_cell_fill(*point, row_from_bottom, 4, height);
+                            for dot in BRAILLE_DOTS[column].iter().take(fill) {
+                                bits |= dot;
+                            }
+                        }
+                        if bits == 0 {
+                            // Blank braille is not whitespace when copied
+                            ' '
+                        } else {
+                            char::from_u32(0x2800 + bits).unwrap_or(' ')
+                        }
+                    }
+                };
+            })
+            .collect();
+        rows.push(line);
+    }
+    return rows;
+}
+
+/// Picks count points from timestamped samples evenly over the window ending at end_ms.
+/// A point takes the last sample at or before its time, points before the first sample are None.
+pub fn sample_window(
+    samples: &[(u64, f64)],
+    end_ms: u64,
+    window_ms: u64,
+    count: usize
+) -> Vec<Option<f64>> {
+    let start_ms = end_ms.saturating_sub(window_ms);
+    let mut points = Vec::with_capacity(count);
+    let mut next = 0;
+    let mut last = None;
+    for i in 0..count {
+        let time_ms = start_ms + ((window_ms as f64) * ((i + 1) as f64) / (count as f64)) as u64;
+        while next < samples.len() && samples[next].0 <= time_ms {
+            last = Some(samples[next].1);
+            next += 1;
+        }
+        points.push(last);
+    }
+    return points;
+}
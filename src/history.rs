@@ -0,0 +1,378 @@
+use std::collections::{ HashMap, VecDeque };
+
+use sysinfo::Pid;
+
+use crate::{
+    constants::{ HISTORY_GRACE_PERIOD_MS, HISTORY_LENGTH, MAX_GRAPH_WINDOW_MS },
+    snapshot::{ ProcessSnapshot, Snapshot },
+};
+
+const SPARKLINE_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Recent values in a ring buffer of HISTORY_LENGTH, with statistics over all values
+pub struct Samples {
+    recent: VecDeque<f64>,
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u64,
+}
+
+impl Samples {
+    fn new() -> Samples {
+        return Samples {
+            recent: VecDeque::with_capacity(HISTORY_LENGTH),
+            min: f64::MAX,
+            max: f64::MIN,
+            sum: 0.0,
+            count: 0,
+        };
+    }
+
+    fn push(&mut self, value: f64) {
+        if self.recent.len() == HISTORY_LENGTH {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(value);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Minimum, average and maximum since the process was first seen
+    pub fn stats(&self) -> (f64, f64, f64) {
+        return (self.min, self.sum / (self.count.max(1) as f64), self.max);
+    }
+
+    /// Draws the most recent values fitting into width as bars. Bars are scaled from zero,
+    /// or from the lowest shown value so that slow growth like a leak stays visible.
+    pub fn sparkline(&self, width: usize, from_zero: bool) -> String {
+        let shown: Vec<f64> = self.recent
+            .iter()
+            .skip(self.recent.len().saturating_sub(width))
+            .copied()
+            .collect();
+        let low = if from_zero { 0.0 } else { shown.iter().copied().fold(f64::MAX, f64::min) };
+        let high = shown.iter().copied().fold(f64::MIN, f64::max);
+        return shown
+            .iter()
+            .map(|value| {
+                if high <= low {
+                    return SPARKLINE_BARS[0];
+                }
+                let level = ((value - low) / (high - low)) * ((SPARKLINE_BARS.len() - 1) as f64);
+                return SPARKLINE_BARS[(level.round() as usize).min(SPARKLINE_BARS.len() - 1)];
+            })
+            .collect();
+    }
+}
+
+pub struct ProcessHistory {
+    /// Tells a process apart from a later one reusing its PID
+    start_time: u64,
+    /// Timestamp of the last snapshot the process was in
+    last_seen_ms: u64,
+    /// CPU usage in percent
+    pub cpu: Samples,
+    /// Resident memory in bytes
+    pub memory: Samples,
+}
+
+/// System usage of one snapshot, as fractions between 0 and 1
+pub struct SystemSample {
+    pub timestamp_ms: u64,
+    pub cpu: f64,
+    pub memory: f64,
+    /// None without swap
+    pub swap: Option<f64>,
+    /// None without graphics cards, a card is None while its usage is unavailable
+    pub gpus: Option<Vec<Option<f64>>>,
+}
+
+/// Disk throughput between the last two snapshots in bytes per second
+#[derive(Clone, Copy)]
+pub struct DiskRates {
+    pub read: f64,
+    pub written: f64,
+}
+
+/// Network throughput between the last two snapshots in bytes per second
+#[derive(Clone, Copy)]
+pub struct NetworkRates {
+    pub received: f64,
+    pub transmitted: f64,
+}
+
+pub struct NetworkHistory {
+    /// Bytes received and transmitted since boot in the last snapshot
+    totals: (u64, u64),
+    /// None until the interface was in two snapshots
+    pub rates: Option<NetworkRates>,
+    /// Bytes per second
+    pub received: Samples,
+    pub transmitted: Samples,
+}
+
+fn get_fraction(used: u64, total: u64) -> Option<f64> {
+    if total == 0 {
+        return None;
+    }
+    return Some((used as f64) / (total as f64));
+}
+
+/// Samples of every process seen in the snapshots, kept for HISTORY_GRACE_PERIOD_MS after exit,
+/// and system usage of the last MAX_GRAPH_WINDOW_MS
+pub struct History {
+    processes: HashMap<Pid, ProcessHistory>,
+    system: VecDeque<SystemSample>,
+    /// Bytes read and written since boot per mount point in the last snapshot
+    disk_totals: HashMap<String, (u64, u64)>,
+    disk_rates: HashMap<String, DiskRates>,
+    /// Interfaces of the last snapshot by name
+    networks: HashMap<String, NetworkHistory>,
+    last_timestamp_ms: u64,
+}
+
+impl History {
+    pub fn new() -> History {
+        return History {
+            processes: HashMap::new(),
+            system: VecDeque::new(),
+            disk_totals: HashMap::new(),
+            disk_rates: HashMap::new(),
+            networks: HashMap::new(),
+            last_timestamp_ms: 0,
+        };
+    }
+
+    pub fn record(&mut self, snapshot: &Snapshot) {
+        if snapshot.timestamp_ms <= self.last_timestamp_ms {
+            // Moving back in a replay would mix up the order of samples
+            self.processes.clear();
+            self.system.clear();
+            self.disk_totals.clear();
+            self.networks.clear();
+        }
+        let elapsed_ms = snapshot.timestamp_ms.saturating_sub(self.last_timestamp_ms);
+        self.record_disks(snapshot, elapsed_ms);
+        self.record_networks(snapshot, elapsed_ms);
+        self.last_timestamp_ms = snapshot.timestamp_ms;
+        for process in &snapshot.processes {
+            let history = self.processes.entry(process.pid).or_insert_with(|| ProcessHistory {
+                start_time: process.start_time,
+                last_seen_ms: 0,
+                cpu: Samples::new(),
+                memory: Samples::new(),
+            });
+            if history.start_time != process.start_time {
+                history.start_time = process.start_time;
+                history.cpu = Samples::new();
+                history.memory = Samples::new();
+            }
+            history.last_seen_ms = snapshot.timestamp_ms;
+            history.cpu.push(process.cpu_usage as f64);
+            history.memory.push(process.memory as f64);
+        }
+        self.processes.retain(|_, history| {
+            return snapshot.timestamp_ms - history.last_seen_ms <= HISTORY_GRACE_PERIOD_MS;
+        });
+        self.system.push_back(SystemSample {
+            timestamp_ms: snapshot.timestamp_ms,
+            cpu: (snapshot.global_cpu_usage as f64) / 100.0,
+            memory: get_fraction(snapshot.used_memory, snapshot.total_memory).unwrap_or(0.0),
+            swap: get_fraction(snapshot.used_swap, snapshot.total_swap),
+            gpus: snapshot.gpus.as_ref().map(|gpus| {
+                return gpus
+                    .iter()
+                    .map(|gpu| gpu.usage.map(|usage| (usage as f64) / 100.0))
+                    .collect();
+            }),
+        });
+        while
+            self.system
+                .front()
+                .is_some_and(|sample| {
+                    return snapshot.timestamp_ms - sample.timestamp_ms > MAX_GRAPH_WINDOW_MS;
+                })
+        {
+            self.system.pop_front();
+        }
+    }
+
+    /// Computes disk throughput from the change of the totals since the previous snapshot
+    fn record_disks(&mut self, snapshot: &Snapshot, elapsed_ms: u64) {
+        let mut disk_totals = HashMap::new();
+        self.disk_rates.clear();
+        for disk in &snapshot.disks {
+            let Some(totals) = disk.total_read_bytes.zip(disk.total_written_bytes) else {
+                continue;
+            };
+            if let Some((read, written)) = self.disk_totals.get(&disk.mount_point) {
+                let seconds = (elapsed_ms as f64) / 1000.0;
+                self.disk_rates.insert(disk.mount_point.clone(), DiskRates {
+                    read: (totals.0.saturating_sub(*read) as f64) / seconds,
+                    written: (totals.1.saturating_sub(*written) as f64) / seconds,
+                });
+            }
+            disk_totals.insert(disk.mount_point.clone(), totals);
+        }
+        self.disk_totals = disk_totals;
+    }
+
+    /// Computes network throughput like record_disks, interfaces that are gone are dropped
+    fn record_networks(&mut self, snapshot: &Snapshot, elapsed_ms: u64) {
+        let mut networks = HashMap::new();
+        for network in &snapshot.networks {
+            let totals = (network.total_received, network.total_transmitted);
+            let history = match self.networks.remove(&network.name) {
+                Some(mut history) => {
+                    let seconds = (elapsed_ms as f64) / 1000.0;
+                    let rates = NetworkRates {
+                        received: (totals.0.saturating_sub(history.totals.0) as f64) / seconds,
+                        transmitted: (totals.1.saturating_sub(history.totals.1) as f64) / seconds,
+                    };
+                    history.received.push(rates.received);
+                    history.transmitted.push(rates.transmitted);
+                    history.rates = Some(rates);
+                    history.totals = totals;
+                    history
+                }
+                None =>
+                    NetworkHistory {
+                        totals,
+                        rates: None,
+                        received: Samples::new(),
+                        transmitted: Samples::new(),
+                    },
+            };
+            networks.insert(network.name.clone(), history);
+        }
+        self.networks = networks;
+    }
+
+    pub fn get_network(&self, name: &str) -> Option<&NetworkHistory> {
+        return self.networks.get(name);
+    }
+
+    /// Throughput of the disk mounted at the mount point, None before the second snapshot
+    pub fn get_disk_rates(&self, mount_point: &str) -> Option<DiskRates> {
+        return self.disk_rates.get(mount_point).copied();
+    }
+
+    /// Timestamped values of the system samples the value exists for
+    pub fn get_system_series(&self, value: impl Fn(&SystemSample) -> Option<f64>) -> Vec<(u64, f64)> {
+        return self.system
+            .iter()
+            .filter_map(|sample| value(sample).map(|value| (sample.timestamp_ms, value)))
+            .collect();
+    }
+
+    /// Latest system sample, None before the first snapshot
+    pub fn last_system_sample(&self) -> Option<&SystemSample> {
+        return self.system.back();
+    }
+
+    pub fn get(&self, process: &ProcessSnapshot) -> Option<&ProcessHistory> {
+        return self.processes
+            .get(&process.pid)
+            .filter(|history| history.start_time == process.start_time);
+    }
+
+    /// History of the process with the PID, including processes that exited recently
+    pub fn get_by_pid(&self, pid: &Pid) -> Option<&ProcessHistory> {
+        return self.processes.get(pid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::export::tests::get_snapshot;
+
+    use super::*;
+
+    fn get_snapshot_without_processes(timestamp_ms: u64) -> Snapshot {
+        let mut snapshot = get_snapshot(timestamp_ms);
+        snapshot.processes.clear();
+        return snapshot;
+    }
+
+    /// Snapshot with disk and network counters read, written, received and transmitted
+    fn get_snapshot_with_counters(timestamp_ms: u64, counters: [u64; 4]) -> Snapshot {
+        let mut snapshot = get_snapshot(timestamp_ms);
+        snapshot.disks[0].total_read_bytes = Some(counters[0]);
+        snapshot.disks[0].total_written_bytes = Some(counters[1]);
+        snapshot.networks[0].total_received = counters[2];
+        snapshot.networks[0].total_transmitted = counters[3];
+        return snapshot;
+    }
+
+    fn get_rates(history: &History) -> [f64; 4] {
+        let disk = history.get_disk_rates("/").unwrap();
+        let network = history.get_network("eth0").unwrap().rates.unwrap();
+        return [disk.read, disk.written, network.received, network.transmitted];
+    }
+
+    #[test]
+    fn samples_keep_most_recent_values() {
+        let mut samples = Samples::new();
+        for value in 0..HISTORY_LENGTH + 5 {
+            samples.push(value as f64);
+        }
+        assert_eq!(samples.recent.len(), HISTORY_LENGTH);
+        assert_eq!(samples.recent.front(), Some(&5.0));
+        assert_eq!(samples.recent.back(), Some(&((HISTORY_LENGTH + 4) as f64)));
+        // Statistics still cover the values pushed out of the buffer
+        let (min, average, max) = samples.stats();
+        assert_eq!(min, 0.0);
+        assert_eq!(average, ((HISTORY_LENGTH + 4) as f64) / 2.0);
+        assert_eq!(max, (HISTORY_LENGTH + 4) as f64);
+    }
+
+    #[test]
+    fn exited_process_is_kept_for_grace_period() {
+        let pid = Pid::from_u32(4242);
+        let mut history = History::new();
+        history.record(&get_snapshot(1000));
+        history.record(&get_snapshot_without_processes(1000 + HISTORY_GRACE_PERIOD_MS));
+        assert!(history.get_by_pid(&pid).is_some());
+        history.record(&get_snapshot_without_processes(1001 + HISTORY_GRACE_PERIOD_MS));
+        assert!(history.get_by_pid(&pid).is_none());
+    }
+
+    #[test]
+    fn process_returning_within_grace_period_keeps_samples() {
+        let snapshot = get_snapshot(1000);
+        let mut history = History::new();
+        history.record(&snapshot);
+        history.record(&get_snapshot_without_processes(2000));
+        history.record(&get_snapshot(1000 + HISTORY_GRACE_PERIOD_MS));
+        let process_history = history.get(&snapshot.processes[0]).unwrap();
+        assert_eq!(process_history.cpu.recent.len(), 2);
+
+        // A new process reusing the PID starts over
+        let mut reused = get_snapshot(2000 + HISTORY_GRACE_PERIOD_MS);
+        reused.processes[0].start_time += 1;
+        history.record(&reused);
+        assert!(history.get(&snapshot.processes[0]).is_none());
+        assert_eq!(history.get(&reused.processes[0]).unwrap().cpu.recent.len(), 1);
+    }
+
+    #[test]
+    fn rates_skip_counter_resets() {
+        let mut history = History::new();
+        history.record(&get_snapshot_with_counters(1000, [1000, 2000, 3000, 4000]));
+        assert!(history.get_disk_rates("/").is_none());
+        assert!(history.get_network("eth0").unwrap().rates.is_none());
+
+        history.record(&get_snapshot_with_counters(3000, [3000, 6000, 7000, 4000]));
+        assert_eq!(get_rates(&history), [1000.0, 2000.0, 2000.0, 0.0]);
+
+        // Counters start over, e.g. after the interface was recreated
+        history.record(&get_snapshot_with_counters(4000, [500, 0, 100, 200]));
+        assert_eq!(get_rates(&history), [0.0, 0.0, 0.0, 0.0]);
+
+        history.record(&get_snapshot_with_counters(4500, [1000, 1000, 600, 200]));
+        assert_eq!(get_rates(&history), [1000.0, 2000.0, 1000.0, 0.0]);
+    }
+}
@@ -1,24 +1,117 @@
+
+mod config;
 mod constants;
+mod disk_columns;
+mod export;
+mod filter;
+mod format;
+mod gpu;
+mod graph;
+mod history;
+mod network_columns;
+mod process_columns;
+mod process_list;
+mod process_signal;
+mod procfs;
+mod recording;
+mod ps;
+mod sampler;
+mod sensor_columns;
+mod snapshot;
 mod state;
 mod run_mode;
+mod terminal;
 mod utils;
 
-use std::io::stdout;
+use std::{ env, io::{ stdout, IsTerminal }, process };
 
-use crossterm::{ cursor, queue };
+use config::{ Command, Config, USAGE };
+use export::{ run_export, ExportFormat };
+use process_columns::{ find_column, DEFAULT_VISIBLE_COLUMNS };
+use ps::{ run_batch, run_ps };
+use recording::{ run_record, Replay };
 use run_mode::run;
-use state::{ Mode, MODE };
-use clearscreen;
+use sampler::Sampler;
+use state::{ App, Mode };
+use terminal::TerminalGuard;
 use utils::set_current_terminal_dimensions;
 
 fn main() {
-    clearscreen::clear().expect("failed to clear");
-    queue!(stdout(), cursor::Hide).unwrap();
-    set_current_terminal_dimensions();
-    unsafe {
-        while MODE != Mode::EXIT {
-            run();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = match Config::load(&args) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            process::exit(2);
+        }
+    };
+    match config.command {
+        Command::HELP => {
+            println!("{USAGE}");
+            return;
+        }
+        Command::VERSION => {
+            println!("task-manager {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Command::TUI if !stdout().is_terminal() => {
+            eprintln!("Error: Not a terminal, use the ps or batch command instead");
+            process::exit(2);
+        }
+        _ => (),
+    }
+    let replay = match &config.command {
+        Command::REPLAY(path) =>
+            match Replay::load(path) {
+                Ok(replay) => Some(replay),
+                Err(error) => {
+                    eprintln!("Error: {error}");
+                    process::exit(1);
+                }
+            }
+        _ => None,
+    };
+    let sampler = match replay {
+        Some(_) => Sampler::idle(config.interval),
+        None => Sampler::start(config.interval, config.sysfs_root.clone()),
+    };
+    let mut app = App::new(
+        DEFAULT_VISIBLE_COLUMNS.iter()
+            .filter_map(|id| find_column(id))
+            .collect(),
+        sampler
+    );
+    app.replay = replay;
+    config.apply(&mut app);
+    match &config.command {
+        Command::PS => {
+            run_ps(&mut app, config.width);
+        }
+        Command::BATCH => {
+            run_batch(&mut app, config.iterations, config.width);
+        }
+        Command::EXPORT(path) => {
+            let Some(format) = config.export_format.or_else(|| ExportFormat::from_path(path)) else {
+                eprintln!("Error: Unknown export format, use a json, csv or ndjson file or --format");
+                process::exit(2);
+            };
+            if let Err(error) = run_export(&mut app, path, format, config.iterations) {
+                eprintln!("Error: {error}");
+                process::exit(1);
+            }
+        }
+        Command::RECORD(path) => {
+            if let Err(error) = run_record(&mut app, path, config.iterations) {
+                eprintln!("Error: {error}");
+                process::exit(1);
+            }
+        }
+        _ => {
+            let _terminal = TerminalGuard::enter();
+            set_current_terminal_dimensions(&mut app.previous_dimensions);
+            while app.mode != Mode::EXIT {
+                run(&mut app);
+            }
         }
     }
-    clearscreen::clear().expect("failed to clear");
 }
@@ -0,0 +1,148 @@
+use std::cmp::Ordering;
+
+use crate::{
+    format::{ format_bytes, format_rate, UnitMode },
+    history::{ NetworkHistory, Samples },
+    process_columns::{ Alignment, SortKey },
+    snapshot::NetworkSnapshot,
+};
+
+/// Interface of the snapshot with its history, None before the history has recorded it
+pub struct NetworkRow<'a> {
+    pub network: &'a NetworkSnapshot,
+    pub history: Option<&'a NetworkHistory>,
+}
+
+impl NetworkRow<'_> {
+    fn received_rate(&self) -> Option<f64> {
+        return self.history.and_then(|history| history.rates).map(|rates| rates.received);
+    }
+
+    fn transmitted_rate(&self) -> Option<f64> {
+        return self.history.and_then(|history| history.rates).map(|rates| rates.transmitted);
+    }
+}
+
+pub struct NetworkColumn {
+    /// Identifier used for picking the column from the command line
+    pub id: &'static str,
+    pub header: &'static str,
+    pub alignment: Alignment,
+    /// Text printed in the table cell
+    pub value: fn(&NetworkRow, UnitMode) -> String,
+    /// Rates drawn as a sparkline instead of value when set
+    pub graph: Option<fn(&NetworkHistory) -> &Samples>,
+    pub sort_key: fn(&NetworkRow) -> SortKey,
+}
+
+impl NetworkColumn {
+    /// Cell text, graph columns are drawn within width
+    pub fn format(&self, row: &NetworkRow, width: usize, units: UnitMode) -> String {
+        return match self.graph.zip(row.history) {
+            Some((samples, history)) => samples(history).sparkline(width, true),
+            None => (self.value)(row, units),
+        };
+    }
+
+    pub fn compare(&self, a: &NetworkRow, b: &NetworkRow) -> Ordering {
+        return (self.sort_key)(a).compare(&(self.sort_key)(b));
+    }
+}
+
+pub fn find_network_column(id: &str) -> Option<usize> {
+    return NETWORK_COLUMNS.iter().position(|column| column.id == id.to_lowercase());
+}
+
+/// Bytes per second, "-" before the rate is known
+fn format_optional_rate(rate: Option<f64>, units: UnitMode) -> String {
+    return rate.map_or(String::from("-"), |rate| format_rate(rate, units));
+}
+
+pub const NETWORK_COLUMNS: [NetworkColumn; 10] = [
+    NetworkColumn {
+        id: "interface",
+        header: "Interface",
+        alignment: Alignment::LEFT,
+        value: |row, _| row.network.name.to_string(),
+        graph: None,
+        sort_key: |row| SortKey::TEXT(row.network.name.to_string()),
+    },
+    NetworkColumn {
+        id: "rx",
+        header: "RX",
+        alignment: Alignment::RIGHT,
+        value: |row, units| format_optional_rate(row.received_rate(), units),
+        graph: None,
+        sort_key: |row| SortKey::NUMBER(row.received_rate().unwrap_or(-1.0)),
+    },
+    NetworkColumn {
+        id: "tx",
+        header: "TX",
+        alignment: Alignment::RIGHT,
+        value: |row, units| format_optional_rate(row.transmitted_rate(), units),
+        graph: None,
+        sort_key: |row| SortKey::NUMBER(row.transmitted_rate().unwrap_or(-1.0)),
+    },
+    NetworkColumn {
+        id: "rx_total",
+        header: "RX total",
+        alignment: Alignment::RIGHT,
+        value: |row, units| format_bytes(row.network.total_received, units),
+        graph: None,
+        sort_key: |row| SortKey::NUMBER(row.network.total_received as f64),
+    },
+    NetworkColumn {
+        id: "tx_total",
+        header: "TX total",
+        alignment: Alignment::RIGHT,
+        value: |row, units| format_bytes(row.network.total_transmitted, units),
+        graph: None,
+        sort_key: |row| SortKey::NUMBER(row.network.total_transmitted as f64),
+    },
+    NetworkColumn {
+        id: "rx_packets",
+        header: "RX packets",
+        alignment: Alignment::RIGHT,
+        value: |row, _| row.network.total_packets_received.to_string(),
+        graph: None,
+        sort_key: |row| SortKey::NUMBER(row.network.total_packets_received as f64),
+    },
+    NetworkColumn {
+        id: "tx_packets",
+        header: "TX packets",
+        alignment: Alignment::RIGHT,
+        value: |row, _| row.network.total_packets_transmitted.to_string(),
+        graph: None,
+        sort_key: |row| SortKey::NUMBER(row.network.total_packets_transmitted as f64),
+    },
+    NetworkColumn {
+        id: "errors",
+        header: "Errors RX/TX",
+        alignment: Alignment::RIGHT,
+        value: |row, _| {
+            format!("{}/{}", row.network.total_errors_received, row.network.total_errors_transmitted)
+        },
+        graph: None,
+        sort_key: |row| {
+            SortKey::NUMBER(
+                (row.network.total_errors_received + row.network.total_errors_transmitted) as f64
+            )
+        },
+    },
+    NetworkColumn {
+        id: "rx_graph",
+        header: "RX history",
+        alignment: Alignment::LEFT,
+        value: |_, _| String::new(),
+        graph: Some(|history| &history.received),
+        sort_key: |row| SortKey::NUMBER(row.received_rate().unwrap_or(-1.0)),
+    },
+    NetworkColumn {
+        id: "tx_graph",
+        header: "TX history",
+        alignment: Alignment::LEFT,
+        value: |_, _| String::new(),
+        graph: Some(|history| &history.transmitted),
+        sort_key: |row| SortKey::NUMBER(row.transmitted_rate().unwrap_or(-1.0)),
+    },
+];
@@ -0,0 +1,233 @@
+use std::cmp::Ordering;
+
+use crate::{
+    format::{ format_bytes, format_elapsed, format_percent, UnitMode },
+    history::{ History, ProcessHistory, Samples },
+    snapshot::ProcessSnapshot,
+    utils::format_timestamp,
+};
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Clone, Copy)]
+pub enum Alignment {
+    LEFT,
+    RIGHT,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+pub enum SortKey {
+    NUMBER(f64),
+    TEXT(String),
+}
+
+impl SortKey {
+    pub fn compare(&self, other: &SortKey) -> Ordering {
+        return match (self, other) {
+            (SortKey::NUMBER(a), SortKey::NUMBER(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (SortKey::TEXT(a), SortKey::TEXT(b)) => a.cmp(b),
+            _ => Ordering::Equal,
+        };
+    }
+}
+
+/// Column drawn as a sparkline of the process history
+pub struct HistoryColumn {
+    pub samples: fn(&ProcessHistory) -> &Samples,
+    /// Scales bars from zero instead of from the lowest shown value
+    pub from_zero: bool,
+}
+
+pub struct ProcessColumn {
+    /// Identifier used for picking the column from the command line
+    pub id: &'static str,
+    pub header: &'static str,
+    pub alignment: Alignment,
+    /// Cell text, also matched by filters, byte sizes are scaled by the unit mode
+    pub value: fn(&ProcessSnapshot, UnitMode) -> String,
+    /// Drawn from the history instead of value when set, value is used until there is history
+    pub history: Option<HistoryColumn>,
+    pub sort_key: fn(&ProcessSnapshot) -> SortKey,
+}
+
+impl ProcessColumn {
+    /// Cell text, sparkline columns are drawn within width
+    pub fn format(
+        &self,
+        process: &ProcessSnapshot,
+        history: &History,
+        width: usize,
+        units: UnitMode
+    ) -> String {
+        let column_history = self.history.as_ref().zip(history.get(process));
+        return match column_history {
+            Some((column, process_history)) => {
+                (column.samples)(process_history).sparkline(width, column.from_zero)
+            }
+            None => (self.value)(process, units),
+        };
+    }
+
+    pub fn compare(&self, a: &ProcessSnapshot, b: &ProcessSnapshot) -> Ordering {
+        return (self.sort_key)(a).compare(&(self.sort_key)(b));
+    }
+}
+
+pub const PROCESS_COLUMNS: [ProcessColumn; 18] = [
+    ProcessColumn {
+        id: "pid",
+        header: "PID",
+        alignment: Alignment::RIGHT,
+        value: |process, _| process.pid.as_u32().to_string(),
+        history: None,
+        sort_key: |process| SortKey::NUMBER(process.pid.as_u32() as f64),
+    },
+    ProcessColumn {
+        id: "name",
+        header: "Name",
+        alignment: Alignment::LEFT,
+        value: |process, _| process.name.to_string(),
+        history: None,
+        sort_key: |process| SortKey::TEXT(process.name.to_string()),
+    },
+    ProcessColumn {
+        id: "cpu",
+        header: "CPU",
+        alignment: Alignment::RIGHT,
+        value: |process, _| format_percent(process.cpu_usage as f64),
+        history: None,
+        sort_key: |process| SortKey::NUMBER(process.cpu_usage as f64),
+    },
+    ProcessColumn {
+        id: "mem",
+        header: "Memory",
+        alignment: Alignment::RIGHT,
+        value: |process, units| format_bytes(process.memory, units),
+        history: None,
+        sort_key: |process| SortKey::NUMBER(process.memory as f64),
+    },
+    ProcessColumn {
+        id: "time",
+        header: "Run time",
+        alignment: Alignment::RIGHT,
+        value: |process, _| format_elapsed(process.run_time),
+        history: None,
+        sort_key: |process| SortKey::NUMBER(process.run_time as f64),
+    },
+    ProcessColumn {
+        id: "user",
+        header: "User",
+        alignment: Alignment::LEFT,
+        value: |process, _| process.user.to_string(),
+        history: None,
+        sort_key: |process| SortKey::TEXT(process.user.to_string()),
+    },
+    ProcessColumn {
+        id: "state",
+        header: "State",
+        alignment: Alignment::LEFT,
+        value: |process, _| process.status.to_string(),
+        history: None,
+        sort_key: |process| SortKey::TEXT(process.status.to_string()),
+    },
+    ProcessColumn {
+        id: "ppid",
+        header: "PPID",
+        alignment: Alignment::RIGHT,
+        value: |process, _| get_parent_pid(process),
+        history: None,
+        sort_key: |process| {
+            SortKey::NUMBER(process.parent.map_or(-1.0, |pid| pid.as_u32() as f64))
+        },
+    },
+    ProcessColumn {
+        id: "threads",
+        header: "Threads",
+        alignment: Alignment::RIGHT,
+        value: |process, _| process.threads.to_string(),
+        history: None,
+        sort_key: |process| SortKey::NUMBER(process.threads as f64),
+    },
+    ProcessColumn {
+        id: "vmem",
+        header: "Virtual memory",
+        alignment: Alignment::RIGHT,
+        value: |process, units| format_bytes(process.virtual_memory, units),
+        history: None,
+        sort_key: |process| SortKey::NUMBER(process.virtual_memory as f64),
+    },
+    ProcessColumn {
+        id: "read",
+        header: "Disk read",
+        alignment: Alignment::RIGHT,
+        value: |process, units| format_bytes(process.read_bytes, units),
+        history: None,
+        sort_key: |process| SortKey::NUMBER(process.read_bytes as f64),
+    },
+    ProcessColumn {
+        id: "write",
+        header: "Disk write",
+        alignment: Alignment::RIGHT,
+        value: |process, units| format_bytes(process.written_bytes, units),
+        history: None,
+        sort_key: |process| SortKey::NUMBER(process.written_bytes as f64),
+    },
+    ProcessColumn {
+        id: "cmd",
+        header: "Command",
+        alignment: Alignment::LEFT,
+        value: |process, _| process.command.to_string(),
+        history: None,
+        sort_key: |process| SortKey::TEXT(process.command.to_string()),
+    },
+    ProcessColumn {
+        id: "exe",
+        header: "Executable",
+        alignment: Alignment::LEFT,
+        value: |process, _| process.executable.to_string(),
+        history: None,
+        sort_key: |process| SortKey::TEXT(process.executable.to_string()),
+    },
+    ProcessColumn {
+        id: "start",
+        header: "Start time",
+        alignment: Alignment::LEFT,
+        value: |process, _| format_timestamp(process.start_time),
+        history: None,
+        sort_key: |process| SortKey::NUMBER(process.start_time as f64),
+    },
+    ProcessColumn {
+        id: "nice",
+        header: "Nice",
+        alignment: Alignment::RIGHT,
+        value: |process, _| process.nice.map_or(String::from("-"), |nice| nice.to_string()),
+        history: None,
+        sort_key: |process| SortKey::NUMBER(process.nice.unwrap_or(0) as f64),
+    },
+    ProcessColumn {
+        id: "cpu_graph",
+        header: "CPU history",
+        alignment: Alignment::LEFT,
+        value: |process, _| format_percent(process.cpu_usage as f64),
+        history: Some(HistoryColumn { samples: |history| &history.cpu, from_zero: true }),
+        sort_key: |process| SortKey::NUMBER(process.cpu_usage as f64),
+    },
+    ProcessColumn {
+        id: "mem_graph",
+        header: "Memory history",
+        alignment: Alignment::LEFT,
+        value: |process, units| format_bytes(process.memory, units),
+        history: Some(HistoryColumn { samples: |history| &history.memory, from_zero: false }),
+        sort_key: |process| SortKey::NUMBER(process.memory as f64),
+    },
+];
+
+pub const DEFAULT_VISIBLE_COLUMNS: [&str; 5] = ["pid", "name", "cpu", "mem", "time"];
+
+/// Returns index of the column in PROCESS_COLUMNS
+pub fn find_column(id: &str) -> Option<usize> {
+    return PROCESS_COLUMNS.iter().position(|column| column.id == id.to_lowercase());
+}
+
+fn get_parent_pid(process: &ProcessSnapshot) -> String {
+    return process.parent.map_or(String::new(), |pid| pid.as_u32().to_string());
+}
@@ -0,0 +1,108 @@
+use std::{ cmp::Ordering, collections::{ HashMap, HashSet } };
+
+use sysinfo::Pid;
+
+use crate::{
+    process_columns::PROCESS_COLUMNS,
+    snapshot::{ ProcessSnapshot, Snapshot },
+    state::App,
+};
+
+fn matches_search(process: &ProcessSnapshot, app: &App) -> bool {
+    if !app.watched_pids.is_empty() && !app.watched_pids.contains(&process.pid) {
+        return false;
+    }
+    if app.user_filter.as_ref().is_some_and(|user| *user != process.user) {
+        return false;
+    }
+    return match &app.search_filter {
+        Some(filter) => filter.matches(process, app.selected_column_index(), app.unit_mode),
+        None => true,
+    };
+}
+
+fn compare_processes(a: &ProcessSnapshot, b: &ProcessSnapshot, app: &App) -> Ordering {
+    let column = &PROCESS_COLUMNS[app.selected_column_index()];
+    return app.sort_direction.apply(column.compare(a, b));
+}
+
+fn get_sorted_processes<'a>(snapshot: &'a Snapshot, app: &App) -> Vec<&'a ProcessSnapshot> {
+    let mut vec: Vec<_> = snapshot.processes
+        .iter()
+        .filter(|process| matches_search(process, app))
+        .collect();
+    vec.sort_by(|a, b| compare_processes(a, b, app));
+    return vec;
+}
+
+/// Pushes process and its visible descendants to rows depth first, prefixing names with branch glyphs
+fn push_process_subtree<'a>(
+    rows: &mut Vec<(&'a ProcessSnapshot, String)>,
+    children: &HashMap<Pid, Vec<&'a ProcessSnapshot>>,
+    collapsed_pids: &[Pid],
+    process: &'a ProcessSnapshot,
+    indent: &str,
+    branch: &str
+) {
+    let process_children = children.get(&process.pid);
+    let is_collapsed = collapsed_pids.contains(&process.pid);
+    let marker = match process_children {
+        Some(_) if is_collapsed => "[+] ",
+        _ => "",
+    };
+    rows.push((process, format!("{indent}{branch}{marker}")));
+    if is_collapsed {
+        return;
+    }
+    let child_indent = match branch {
+        "├─ " => format!("{indent}│  "),
+        "└─ " => format!("{indent}   "),
+        _ => indent.to_string(),
+    };
+    if let Some(process_children) = process_children {
+        for (i, child) in process_children.iter().enumerate() {
+            let child_branch = if i == process_children.len() - 1 { "└─ " } else { "├─ " };
+            push_process_subtree(
+                rows,
+                children,
+                collapsed_pids,
+                child,
+                child_indent.as_str(),
+                child_branch
+            );
+        }
+    }
+}
+
+/// Returns processes nested under their parents, with siblings sorted by the selected column.
+/// Processes whose parent is filtered out or missing are shown as roots.
+fn get_tree_processes<'a>(snapshot: &'a Snapshot, app: &App) -> Vec<(&'a ProcessSnapshot, String)> {
+    let processes = get_sorted_processes(snapshot, app);
+    let visible: HashSet<Pid> = processes.iter().map(|process| process.pid).collect();
+    let mut roots = Vec::new();
+    let mut children: HashMap<Pid, Vec<&ProcessSnapshot>> = HashMap::new();
+    for process in processes {
+        match process.parent {
+            Some(parent) if visible.contains(&parent) && parent != process.pid => {
+                children.entry(parent).or_default().push(process);
+            }
+            _ => roots.push(process),
+        }
+    }
+    let mut rows = Vec::new();
+    for root in roots {
+        push_process_subtree(&mut rows, &children, &app.collapsed_pids, root, "", "");
+    }
+    return rows;
+}
+
+/// Returns processes to print with a prefix for the Name column
+pub fn get_process_rows<'a>(snapshot: &'a Snapshot, app: &App) -> Vec<(&'a ProcessSnapshot, String)> {
+    if app.tree_view {
+        return get_tree_processes(snapshot, app);
+    }
+    return get_sorted_processes(snapshot, app)
+        .into_iter()
+        .map(|process| (process, String::new()))
+        .collect();
+}
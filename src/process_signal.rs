@@ -0,0 +1,108 @@
+use std::io::Error;
+
+use sysinfo::{ Pid, ProcessRefreshKind, ProcessesToUpdate, System };
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Clone, Copy)]
+pub enum ProcessSignal {
+    TERM,
+    KILL,
+    HUP,
+    INT,
+    STOP,
+    CONT,
+    USR1,
+    USR2,
+}
+
+pub const PROCESS_SIGNALS: [ProcessSignal; 8] = [
+    ProcessSignal::TERM,
+    ProcessSignal::KILL,
+    ProcessSignal::HUP,
+    ProcessSignal::INT,
+    ProcessSignal::STOP,
+    ProcessSignal::CONT,
+    ProcessSignal::USR1,
+    ProcessSignal::USR2,
+];
+
+impl ProcessSignal {
+    /// Parses signal from its name, with or without the "SIG" prefix, or from its number
+    pub fn from_name(name: &str) -> Option<ProcessSignal> {
+        let upper = name.to_uppercase();
+        let stripped = upper.strip_prefix("SIG").unwrap_or(upper.as_str());
+        return PROCESS_SIGNALS.into_iter().find(|signal| {
+            return signal.name() == stripped || signal.number().to_string() == stripped;
+        });
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            ProcessSignal::TERM => "TERM",
+            ProcessSignal::KILL => "KILL",
+            ProcessSignal::HUP => "HUP",
+            ProcessSignal::INT => "INT",
+            ProcessSignal::STOP => "STOP",
+            ProcessSignal::CONT => "CONT",
+            ProcessSignal::USR1 => "USR1",
+            ProcessSignal::USR2 => "USR2",
+        };
+    }
+
+    /// Key used for picking the signal in signal mode
+    pub fn key(&self) -> char {
+        return match self {
+            ProcessSignal::TERM => 't',
+            ProcessSignal::KILL => 'k',
+            ProcessSignal::HUP => 'h',
+            ProcessSignal::INT => 'i',
+            ProcessSignal::STOP => 's',
+            ProcessSignal::CONT => 'c',
+            ProcessSignal::USR1 => '1',
+            ProcessSignal::USR2 => '2',
+        };
+    }
+
+    pub fn number(&self) -> i32 {
+        return match self {
+            ProcessSignal::TERM => libc::SIGTERM,
+            ProcessSignal::KILL => libc::SIGKILL,
+            ProcessSignal::HUP => libc::SIGHUP,
+            ProcessSignal::INT => libc::SIGINT,
+            ProcessSignal::STOP => libc::SIGSTOP,
+            ProcessSignal::CONT => libc::SIGCONT,
+            ProcessSignal::USR1 => libc::SIGUSR1,
+            ProcessSignal::USR2 => libc::SIGUSR2,
+        };
+    }
+}
+
+pub fn send_signal(pid: Pid, signal: ProcessSignal) -> Result<(), Error> {
+    let result = unsafe { libc::kill(pid.as_u32() as libc::pid_t, signal.number()) };
+    if result == 0 {
+        return Ok(());
+    }
+    return Err(Error::last_os_error());
+}
+
+/// Start time of the process with the PID in seconds since the epoch, None when there is none
+pub fn read_start_time(pid: Pid) -> Option<u64> {
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), ProcessRefreshKind::new());
+    return sys.process(pid).map(|process| process.start_time());
+}
+
+/// Formats the outcome of sending a signal for printing on the input row
+pub fn format_signal_result(pid: Pid, signal: ProcessSignal, result: &Result<(), Error>) -> String {
+    return match result {
+        Ok(()) => format!("Sent SIG{} to {}", signal.name(), pid.as_u32()),
+        Err(error) => {
+            let code = match error.raw_os_error() {
+                Some(libc::EPERM) => "EPERM",
+                Some(libc::ESRCH) => "ESRCH",
+                _ => "Error",
+            };
+            format!("{}: SIG{} to {} failed ({})", code, signal.name(), pid.as_u32(), error)
+        }
+    };
+}
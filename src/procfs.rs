@@ -0,0 +1,47 @@
+use std::{ collections::HashMap, fs };
+
+use sysinfo::Pid;
+
+/// Returns field of /proc/<pid>/stat by its 1-based number as documented in proc(5),
+/// for values sysinfo does not expose
+pub fn read_stat_field(pid: &Pid, field: usize) -> Option<String> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid.as_u32())).ok()?;
+    // Process name may contain spaces, so fields are counted from the closing parenthesis
+    let fields = stat.rsplit_once(')')?.1;
+    return fields
+        .split_whitespace()
+        .nth(field.checked_sub(3)?)
+        .map(|value| value.to_string());
+}
+
+/// Returns value of a kB field in /proc/<pid>/status, e.g. "VmRSS"
+pub fn read_status_kb(pid: &Pid, key: &str) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid.as_u32())).ok()?;
+    let line = status.lines().find(|line| line.starts_with(format!("{key}:").as_str()))?;
+    return line.split_whitespace().nth(1)?.parse().ok();
+}
+
+/// Returns user and system CPU time of the process in seconds
+pub fn read_cpu_times(pid: &Pid) -> Option<(f64, f64)> {
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) } as f64;
+    let user_ticks: f64 = read_stat_field(pid, 14)?.parse().ok()?;
+    let system_ticks: f64 = read_stat_field(pid, 15)?.parse().ok()?;
+    return Some((user_ticks / ticks_per_second, system_ticks / ticks_per_second));
+}
+
+/// Returns bytes read and written since boot per block device name, e.g. "sda1" or "dm-0"
+pub fn read_disk_stats() -> HashMap<String, (u64, u64)> {
+    let Ok(stats) = fs::read_to_string("/proc/diskstats") else {
+        return HashMap::new();
+    };
+    // Sectors are counted in 512 bytes regardless of the device, see the kernel iostats docs
+    return stats
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let sectors_read: u64 = fields.get(5)?.parse().ok()?;
+            let sectors_written: u64 = fields.get(9)?.parse().ok()?;
+            return Some((fields[2].to_string(), (sectors_read * 512, sectors_written * 512)));
+        })
+        .collect();
+}
@@ -0,0 +1,134 @@
+use std::io::{ stdout, Write };
+
+use colored::Colorize;
+
+use crate::{
+    constants::SPARKLINE_WIDTH,
+    format::{ format_bytes, format_percent },
+    process_columns::{ Alignment, PROCESS_COLUMNS },
+    process_list::get_process_rows,
+    snapshot::Snapshot,
+    state::App,
+    utils::format_timestamp,
+};
+
+/// Formats the visible columns of the filtered and sorted processes as plain text lines
+fn format_process_list(snapshot: &Snapshot, app: &App) -> Vec<String> {
+    let columns: Vec<_> = app.visible_columns
+        .iter()
+        .map(|index| &PROCESS_COLUMNS[*index])
+        .collect();
+    let rows: Vec<Vec<String>> = get_process_rows(snapshot, app)
+        .iter()
+        .map(|(process, prefix)| {
+            return columns
+                .iter()
+                .map(|column| {
+                    if column.id == "name" {
+                        return format!("{}{}", prefix, (column.value)(process, app.unit_mode));
+                    }
+                    return column.format(process, &app.history, SPARKLINE_WIDTH, app.unit_mode);
+                })
+                .collect();
+        })
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            return rows
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain([column.header.chars().count()])
+                .max()
+                .unwrap_or(0);
+        })
+        .collect();
+    let format_row = |cells: Vec<&str>| {
+        let formatted: Vec<String> = cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                let width = widths[i];
+                // Padding the last left aligned column would only add trailing spaces
+                return match columns[i].alignment {
+                    Alignment::RIGHT => format!("{cell:>width$}"),
+                    Alignment::LEFT if i == cells.len() - 1 => cell.to_string(),
+                    Alignment::LEFT => format!("{cell:<width$}"),
+                };
+            })
+            .collect();
+        return formatted.join("  ");
+    };
+    let mut lines = vec![format_row(columns.iter().map(|column| column.header).collect())];
+    for row in &rows {
+        lines.push(format_row(row.iter().map(|cell| cell.as_str()).collect()));
+    }
+    return lines;
+}
+
+/// Cuts a line to at most width characters
+fn fit_width(line: &str, width: Option<usize>) -> &str {
+    let Some(width) = width else {
+        return line;
+    };
+    return match line.char_indices().nth(width) {
+        Some((end, _)) => &line[..end],
+        None => line,
+    };
+}
+
+/// Writes the process table, returns an error once stdout is closed, e.g. when piped into head
+fn write_process_list(
+    out: &mut impl Write,
+    snapshot: &Snapshot,
+    app: &App,
+    width: Option<usize>
+) -> std::io::Result<()> {
+    for (i, line) in format_process_list(snapshot, app).iter().enumerate() {
+        let line = fit_width(line, width);
+        if i == 0 {
+            writeln!(out, "{}", line.bold())?;
+        } else {
+            writeln!(out, "{line}")?;
+        }
+    }
+    return out.flush();
+}
+
+/// Prints the process table of the first snapshot and returns, used by the ps command
+pub fn run_ps(app: &mut App, width: Option<usize>) {
+    let snapshot = app.sampler.snapshots.recv().expect("sampler thread stopped");
+    app.history.record(&snapshot);
+    let _ = write_process_list(&mut stdout().lock(), &snapshot, app, width);
+}
+
+/// Prints a summary line and the process table for every snapshot, runs until interrupted
+/// when iterations is None. Never touches the terminal, so it works without a TTY.
+pub fn run_batch(app: &mut App, iterations: Option<u64>, width: Option<usize>) {
+    let mut iteration = 0;
+    while iterations.is_none_or(|iterations| iteration < iterations) {
+        let snapshot = app.sampler.snapshots.recv().expect("sampler thread stopped");
+        app.history.record(&snapshot);
+        iteration += 1;
+        let summary = format!(
+            "{} UTC  CPU usage: {}  Memory: {} / {}  Processes: {}",
+            format_timestamp(snapshot.timestamp_ms / 1000),
+            format_percent(snapshot.global_cpu_usage as f64),
+            format_bytes(snapshot.used_memory, app.unit_mode),
+            format_bytes(snapshot.total_memory, app.unit_mode),
+            snapshot.processes.len()
+        );
+        let mut out = stdout().lock();
+        let result = (|| {
+            if iteration > 1 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{}", fit_width(&summary, width))?;
+            return write_process_list(&mut out, &snapshot, app, width);
+        })();
+        if result.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    fs::File,
+    io::{ BufRead, BufReader, Write },
+    path::Path,
+    sync::Arc,
+    time::Instant,
+};
+
+use flate2::{ read::MultiGzDecoder, write::GzEncoder, Compression };
+
+use crate::{
+    export::{ export_unfiltered_snapshot, ExportedSnapshot },
+    snapshot::Snapshot,
+    state::App,
+};
+
+/// Writes every snapshot as its own gzip member holding one NDJSON line of the export schema.
+/// Members are complete on their own, so a recording stopped by a signal stays readable.
+/// All processes are recorded, filter and sort order are applied when replaying.
+pub fn run_record(app: &mut App, path: &Path, iterations: Option<u64>) -> Result<(), String> {
+    let mut file = File::create(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let mut iteration = 0;
+    while iterations.is_none_or(|iterations| iteration < iterations) {
+        let snapshot = app.sampler.snapshots.recv().expect("sampler thread stopped");
+        iteration += 1;
+        write_snapshot(&mut file, &snapshot)?;
+    }
+    return Ok(());
+}
+
+/// Appends the snapshot as a gzip member and flushes it to the file
+fn write_snapshot(file: &mut File, snapshot: &Snapshot) -> Result<(), String> {
+    let mut encoder = GzEncoder::new(&mut *file, Compression::default());
+    serde_json
+        ::to_writer(&mut encoder, &export_unfiltered_snapshot(snapshot))
+        .map_err(|error| error.to_string())?;
+    writeln!(encoder).map_err(|error| error.to_string())?;
+    encoder.finish().map_err(|error| error.to_string())?;
+    return file.flush().map_err(|error| error.to_string());
+}
+
+/// Reads the snapshots of a recording. A cut off last snapshot, e.g. when the disk ran full,
+/// is dropped instead of failing the whole recording.
+fn load_snapshots(path: &Path) -> Result<Vec<Arc<Snapshot>>, String> {
+    let file = File::open(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let mut snapshots = Vec::new();
+    for (i, line) in BufReader::new(MultiGzDecoder::new(file)).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) if !snapshots.is_empty() => {
+                break;
+            }
+            Err(error) => {
+                return Err(format!("{}: {}", path.display(), error));
+            }
+        };
+        let snapshot = serde_json
+            ::from_str::<ExportedSnapshot>(&line)
+            .map_err(|error| error.to_string())
+            .and_then(|snapshot| snapshot.import())
+            .map_err(|error| format!("{}: snapshot {}: {}", path.display(), i + 1, error))?;
+        snapshots.push(Arc::new(snapshot));
+    }
+    if snapshots.is_empty() {
+        return Err(format!("{}: recording is empty", path.display()));
+    }
+    return Ok(snapshots);
+}
+
+/// Position in a loaded recording, played back in recorded time scaled by speed
+pub struct Replay {
+    snapshots: Vec<Arc<Snapshot>>,
+    position: usize,
+    pub playing: bool,
+    /// Multiplier of the recorded time
+    pub speed: f64,
+    /// Recorded time in milliseconds reached by playing
+    clock_ms: f64,
+    last_tick: Instant,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        let snapshots = load_snapshots(path)?;
+        let clock_ms = snapshots[0].timestamp_ms as f64;
+        return Ok(Replay {
+            snapshots,
+            position: 0,
+            playing: true,
+            speed: 1.0,
+            clock_ms,
+            last_tick: Instant::now(),
+        });
+    }
+
+    pub fn current(&self) -> Arc<Snapshot> {
+        return self.snapshots[self.position].clone();
+    }
+
+    pub fn position(&self) -> usize {
+        return self.position;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.snapshots.len();
+    }
+
+    /// Moves to the snapshot reached by the time passed since the last call
+    pub fn advance(&mut self) {
+        let now = Instant::now();
+        if self.playing {
+            self.clock_ms += (now - self.last_tick).as_secs_f64() * 1000.0 * self.speed;
+        }
+        self.last_tick = now;
+        while
+            self.position + 1 < self.snapshots.len() &&
+            (self.snapshots[self.position + 1].timestamp_ms as f64) <= self.clock_ms
+        {
+            self.position += 1;
+        }
+        if self.position + 1 == self.snapshots.len() {
+            self.playing = false;
+        }
+    }
+
+    fn seek(&mut self, position: usize) {
+        self.position = position.min(self.snapshots.len() - 1);
+        self.clock_ms = self.snapshots[self.position].timestamp_ms as f64;
+        self.last_tick = Instant::now();
+    }
+
+    pub fn toggle_playing(&mut self) {
+        if !self.playing && self.position + 1 == self.snapshots.len() {
+            // Playing at the end starts over
+            self.seek(0);
+        }
+        self.playing = !self.playing;
+        self.last_tick = Instant::now();
+    }
+
+    /// Pauses and moves the given number of snapshots forward or back
+    pub fn step(&mut self, steps: isize) {
+        self.playing = false;
+        self.seek(self.position.saturating_add_signed(steps));
+    }
+
+    pub fn scale_speed(&mut self, factor: f64) {
+        self.speed = (self.speed * factor).clamp(1.0 / 64.0, 1024.0);
+    }
+
+    /// Moves to the last snapshot taken at or before the timestamp, or the first snapshot
+    pub fn jump_to(&mut self, timestamp_ms: u64) {
+        let position = self.snapshots
+            .partition_point(|snapshot| snapshot.timestamp_ms <= timestamp_ms)
+            .saturating_sub(1);
+        self.seek(position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ env, fs, path::PathBuf, process };
+
+    use crate::export::tests::get_snapshot;
+
+    use super::*;
+
+    /// Recording file below the temp dir, removed when dropped
+    struct RecordingFixture {
+        path: PathBuf,
+    }
+
+    impl RecordingFixture {
+        fn new(name: &str) -> RecordingFixture {
+            let file_name = format!("task-manager-{}-{}.ndjson.gz", process::id(), name);
+            return RecordingFixture { path: env::temp_dir().join(file_name) };
+        }
+
+        /// Records a snapshot for each timestamp, returns the file length after each one
+        fn record(&self, timestamps: &[u64]) -> Vec<u64> {
+            let mut file = File::create(&self.path).unwrap();
+            return timestamps
+                .iter()
+                .map(|timestamp_ms| {
+                    write_snapshot(&mut file, &get_snapshot(*timestamp_ms)).unwrap();
+                    return file.metadata().unwrap().len();
+                })
+                .collect();
+        }
+
+        fn truncate(&self, length: u64) {
+            File::options().write(true).open(&self.path).unwrap().set_len(length).unwrap();
+        }
+    }
+
+    impl Drop for RecordingFixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    fn get_timestamp(replay: &Replay) -> u64 {
+        return replay.current().timestamp_ms;
+    }
+
+    #[test]
+    fn replays_snapshots_in_recorded_order() {
+        let fixture = RecordingFixture::new("order");
+        fixture.record(&[1000, 2000, 3000, 4000]);
+        let mut replay = Replay::load(&fixture.path).unwrap();
+        assert_eq!(replay.len(), 4);
+        let mut timestamps = vec![get_timestamp(&replay)];
+        for _ in 1..replay.len() {
+            replay.step(1);
+            timestamps.push(get_timestamp(&replay));
+        }
+        assert_eq!(timestamps, [1000, 2000, 3000, 4000]);
+        assert_eq!(replay.current().processes[0].name, "java");
+    }
+
+    #[test]
+    fn stepping_stops_at_both_ends() {
+        let fixture = RecordingFixture::new("step");
+        fixture.record(&[1000, 2000, 3000]);
+        let mut replay = Replay::load(&fixture.path).unwrap();
+        replay.step(-1);
+        assert_eq!(replay.position(), 0);
+        replay.step(2);
+        replay.step(1);
+        assert_eq!(replay.position(), 2);
+        replay.step(isize::MAX);
+        assert_eq!(replay.position(), 2);
+        assert!(!replay.playing);
+        replay.step(isize::MIN);
+        assert_eq!(replay.position(), 0);
+    }
+
+    #[test]
+    fn jump_to_picks_last_snapshot_at_or_before_time() {
+        let fixture = RecordingFixture::new("jump");
+        fixture.record(&[1000, 2000, 3000]);
+        let mut replay = Replay::load(&fixture.path).unwrap();
+        replay.jump_to(2500);
+        assert_eq!(get_timestamp(&replay), 2000);
+        replay.jump_to(3000);
+        assert_eq!(get_timestamp(&replay), 3000);
+        replay.jump_to(u64::MAX);
+        assert_eq!(get_timestamp(&replay), 3000);
+        replay.jump_to(1000);
+        assert_eq!(get_timestamp(&replay), 1000);
+        // Before the recording starts
+        replay.jump_to(0);
+        assert_eq!(get_timestamp(&replay), 1000);
+    }
+
+    #[test]
+    fn truncated_last_snapshot_is_dropped() {
+        let fixture = RecordingFixture::new("truncated");
+        let lengths = fixture.record(&[1000, 2000, 3000]);
+        fixture.truncate((lengths[1] + lengths[2]) / 2);
+        let replay = Replay::load(&fixture.path).unwrap();
+        assert_eq!(replay.len(), 2);
+        assert_eq!(get_timestamp(&replay), 1000);
+
+        // Without a complete snapshot the recording cannot be replayed
+        fixture.truncate(lengths[0] / 2);
+        assert!(Replay::load(&fixture.path).is_err());
+    }
+}
@@ -0,0 +1,312 @@
+use std::{ collections::HashMap, io::{ stdout, Write }, time::Duration };
+
+use crossterm::{ cursor, event::{ poll, read, Event, KeyCode, KeyEvent, KeyEventKind }, queue };
+use sysinfo::{
+    Groups,
+    Pid,
+    Process,
+    ProcessRefreshKind,
+    ProcessesToUpdate,
+    System,
+    UpdateKind,
+    Users,
+};
+
+use super::{ handle_control_key, take_resumed };
+use crate::{
+    constants::CYCLE_WAIT_TIME_MS,
+    format::{ format_bytes, format_elapsed, format_percent, UnitMode },
+    history::ProcessHistory,
+    procfs::{ read_cpu_times, read_status_kb },
+    snapshot::{ get_process_name, get_process_user },
+    state::{ App, Mode, SelectedProcess },
+    utils::{
+        clear_screen_on_dimension_changed,
+        empty_row,
+        format_timestamp,
+        get_terminal_dimensions,
+        print_on_last_row,
+        print_on_position,
+        wrap_text,
+    },
+};
+
+/// Formats a field of /proc/<pid>/status, whose kB are 1024 bytes
+fn format_status_kb(pid: &Pid, key: &str, units: UnitMode) -> String {
+    return read_status_kb(pid, key).map_or(String::from("-"), |kb| {
+        return format_bytes(kb.saturating_mul(1024), units);
+    });
+}
+
+fn get_process_group(process: &Process, groups: &Groups) -> String {
+    return match process.group_id() {
+        Some(gid) =>
+            match groups.list().iter().find(|group| *group.id() == gid) {
+                Some(group) => format!("{} ({})", group.name(), *gid),
+                None => gid.to_string(),
+            }
+        None => String::from("-"),
+    };
+}
+
+/// Returns the process followed by its ancestors, e.g. "1234 bash <- 1 init"
+fn get_parent_chain(pid: &Pid, sys: &System) -> String {
+    let mut chain = Vec::new();
+    let mut current = Some(*pid);
+    while let Some(current_pid) = current {
+        let Some(process) = sys.process(current_pid) else {
+            chain.push(current_pid.as_u32().to_string());
+            break;
+        };
+        chain.push(format!("{} {}", current_pid.as_u32(), get_process_name(process)));
+        current = process.parent().filter(|parent| *parent != current_pid);
+        // Guard against reparenting loops while the process list changes
+        if chain.len() > 64 {
+            break;
+        }
+    }
+    return chain.join(" <- ");
+}
+
+/// Returns min/avg/max since the process was first seen and sparklines of recent samples
+fn get_history_details(
+    history: &ProcessHistory,
+    graph_width: usize,
+    units: UnitMode
+) -> Vec<(String, String)> {
+    let (cpu_min, cpu_avg, cpu_max) = history.cpu.stats();
+    let (memory_min, memory_avg, memory_max) = history.memory.stats();
+    return vec![
+        (
+            String::from("CPU usage (session)"),
+            format!(
+                "min {}, avg {}, max {}",
+                format_percent(cpu_min),
+                format_percent(cpu_avg),
+                format_percent(cpu_max)
+            ),
+        ),
+        (String::from("CPU history"), history.cpu.sparkline(graph_width, true)),
+        (
+            String::from("Memory (session)"),
+            format!(
+                "min {}, avg {}, max {}",
+                format_bytes(memory_min as u64, units),
+                format_bytes(memory_avg as u64, units),
+                format_bytes(memory_max as u64, units)
+            ),
+        ),
+        (String::from("Memory history"), history.memory.sparkline(graph_width, false)),
+    ];
+}
+
+/// Returns label and value pairs describing the process
+fn get_process_details(
+    pid: &Pid,
+    process: &Process,
+    sys: &System,
+    users: &Users,
+    groups: &Groups,
+    history_details: Vec<(String, String)>,
+    units: UnitMode
+) -> Vec<(String, String)> {
+    let uid = process.user_id().map_or(String::new(), |uid| format!(" ({})", **uid));
+    let cpu_time = match read_cpu_times(pid) {
+        Some((user, system)) =>
+            format!("{:.2} s (user {:.2} s, system {:.2} s)", user + system, user, system),
+        None => String::from("-"),
+    };
+    let disk_usage = process.disk_usage();
+    let command: Vec<_> = process
+        .cmd()
+        .iter()
+        .map(|arg| arg.to_string_lossy())
+        .collect();
+    let mut details = vec![
+        (String::from("PID"), pid.as_u32().to_string()),
+        (String::from("Name"), get_process_name(process)),
+        (String::from("Status"), process.status().to_string()),
+        (String::from("User"), format!("{}{}", get_process_user(process, users), uid)),
+        (String::from("Group"), get_process_group(process, groups)),
+        (String::from("Parent chain"), get_parent_chain(pid, sys)),
+        (String::from("Start time"), format_timestamp(process.start_time())),
+        (String::from("Run time"), format_elapsed(process.run_time())),
+        (String::from("CPU usage"), format_percent(process.cpu_usage() as f64)),
+        (String::from("CPU time"), cpu_time),
+        (String::from("Resident memory"), format_bytes(process.memory(), units)),
+        (String::from("Virtual memory"), format_bytes(process.virtual_memory(), units)),
+        (String::from("Anonymous memory"), format_status_kb(pid, "RssAnon", units)),
+        (String::from("File memory"), format_status_kb(pid, "RssFile", units)),
+        (String::from("Shared memory"), format_status_kb(pid, "RssShmem", units)),
+        (String::from("Swap"), format_status_kb(pid, "VmSwap", units)),
+    ];
+    details.extend(history_details);
+    details.extend([
+        (
+            String::from("Disk read"),
+            format!(
+                "{} (total {})",
+                format_bytes(disk_usage.read_bytes, units),
+                format_bytes(disk_usage.total_read_bytes, units)
+            ),
+        ),
+        (
+            String::from("Disk written"),
+            format!(
+                "{} (total {})",
+                format_bytes(disk_usage.written_bytes, units),
+                format_bytes(disk_usage.total_written_bytes, units)
+            ),
+        ),
+        (
+            String::from("Executable"),
+            process.exe().map_or(String::from("-"), |exe| exe.display().to_string()),
+        ),
+        (
+            String::from("Working directory"),
+            process.cwd().map_or(String::from("-"), |cwd| cwd.display().to_string()),
+        ),
+        (String::from("Command line"), command.join(" ")),
+        (String::from("Environment"), String::new()),
+    ]);
+    for variable in process.environ() {
+        details.push((String::new(), variable.to_string_lossy().to_string()));
+    }
+    return details;
+}
+
+/// Formats details into lines wrapped to terminal width, values aligned after the labels
+fn format_detail_lines(details: &[(String, String)], width: u16) -> Vec<String> {
+    let label_width = details
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0) + 2;
+    let value_width = (width as usize).saturating_sub(label_width).max(1);
+    let mut lines = Vec::new();
+    for (label, value) in details {
+        for (i, chunk) in wrap_text(value, value_width).iter().enumerate() {
+            let label = if i == 0 && !label.is_empty() { format!("{label}:") } else { String::new() };
+            lines.push(format!("{:<label_width$}{}", label, chunk));
+        }
+    }
+    return lines;
+}
+
+fn print_detail_lines(lines: &[String], scroll_offset: usize) {
+    let (width, height) = get_terminal_dimensions();
+    for row in 0..height - 1 {
+        let line = lines.get(scroll_offset + (row as usize)).map_or("", |line| line.as_str());
+        let padding = (width as usize).saturating_sub(line.chars().count());
+        print_on_position(format!("{}{}", line, " ".repeat(padding)).as_str(), row, 0);
+    }
+}
+
+/// The process followed by its ancestors in the latest snapshot, the only processes refreshed
+fn get_refreshed_pids(pid: Pid, app: &App) -> Vec<Pid> {
+    let mut pids = vec![pid];
+    let Some(snapshot) = &app.snapshot else {
+        return pids;
+    };
+    let parents: HashMap<Pid, Pid> = snapshot.processes
+        .iter()
+        .filter_map(|process| Some((process.pid, process.parent?)))
+        .collect();
+    while let Some(parent) = parents.get(&pids[pids.len() - 1]) {
+        // Guard against reparenting loops, like get_parent_chain
+        if pids.contains(parent) || pids.len() > 64 {
+            break;
+        }
+        pids.push(*parent);
+    }
+    return pids;
+}
+
+/// Fields shown in the detail view, the command line, executable and owner are read once
+fn get_detail_refresh_kind() -> ProcessRefreshKind {
+    return ProcessRefreshKind::new()
+        .with_cpu()
+        .with_memory()
+        .with_disk_usage()
+        .with_user(UpdateKind::OnlyIfNotSet)
+        .with_exe(UpdateKind::OnlyIfNotSet)
+        .with_cmd(UpdateKind::OnlyIfNotSet)
+        .with_cwd(UpdateKind::Always);
+}
+
+pub fn run_detail_mode(app: &mut App) {
+    let Some(SelectedProcess { pid, name, .. }) = app.selected_process.clone() else {
+        app.mode = Mode::PRINT;
+        return;
+    };
+    let mut sys = System::new();
+    let users = Users::new_with_refreshed_list();
+    let groups = Groups::new_with_refreshed_list();
+    let mut scroll_offset: usize = 0;
+    clearscreen::clear().expect("failed to clear");
+    while app.mode == Mode::DETAIL {
+        take_resumed(app);
+        clear_screen_on_dimension_changed(&mut app.previous_dimensions);
+        let (width, height) = get_terminal_dimensions();
+        // Keeps the history growing while the process table is not shown
+        if !app.paused {
+            if let Some(snapshot) = app.sampler.snapshots.try_iter().last() {
+                app.show_snapshot(snapshot);
+            }
+        }
+        let history_details = app.history
+            .get_by_pid(&pid)
+            .map_or(Vec::new(), |history| {
+                return get_history_details(history, (width as usize) / 2, app.unit_mode);
+            });
+        // Runs on the UI thread, so only the shown process and its parent chain are refreshed
+        let pids = get_refreshed_pids(pid, app);
+        sys.refresh_processes_specifics(ProcessesToUpdate::Some(&pids), get_detail_refresh_kind());
+        let lines = match sys.process(pid) {
+            Some(process) => {
+                let details = get_process_details(
+                    &pid,
+                    process,
+                    &sys,
+                    &users,
+                    &groups,
+                    history_details,
+                    app.unit_mode
+                );
+                format_detail_lines(&details, width)
+            }
+            None => {
+                let mut lines = vec![format!("Process {} ({}) has exited", pid.as_u32(), name)];
+                lines.extend(format_detail_lines(&history_details, width));
+                lines
+            }
+        };
+        let max_offset = lines.len().saturating_sub((height - 1) as usize);
+        scroll_offset = scroll_offset.min(max_offset);
+        print_detail_lines(&lines, scroll_offset);
+        print_on_last_row("Up/Down - Scroll, Esc - Return to process table");
+        stdout().flush().expect("failed to flush");
+
+        if poll(Duration::from_millis(CYCLE_WAIT_TIME_MS)).is_ok_and(|e| { e }) {
+            let event = read().unwrap();
+            if handle_control_key(app, &event) {
+                continue;
+            }
+            match event {
+                Event::Key(KeyEvent { code: KeyCode::Esc, kind: KeyEventKind::Press, .. }) => {
+                    app.mode = Mode::PRINT;
+                }
+                Event::Key(KeyEvent { code: KeyCode::Up, kind: KeyEventKind::Press, .. }) => {
+                    scroll_offset = scroll_offset.saturating_sub(1);
+                }
+                Event::Key(KeyEvent { code: KeyCode::Down, kind: KeyEventKind::Press, .. }) => {
+                    scroll_offset += 1;
+                }
+                _ => (),
+            }
+        }
+    }
+    clearscreen::clear().expect("failed to clear");
+    empty_row(get_terminal_dimensions().1);
+    queue!(stdout(), cursor::Hide).unwrap();
+}
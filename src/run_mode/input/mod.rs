@@ -1,117 +1,508 @@
-use std::io::{ stdout, Write };
+use std::{ io::{ stdout, Write }, path::Path, time::Duration };
 
-use crossterm::{ cursor, event::{ read, Event, KeyCode, KeyEvent, KeyEventKind }, queue };
+use crossterm::{
+    cursor,
+    event::{ poll, read, Event, KeyCode, KeyEvent, KeyEventKind },
+    queue,
+};
 
+use super::{ handle_control_key, take_resumed };
 use crate::{
-    state::{ Mode, MODE },
+    config::{ parse_graph_window, parse_interval },
+    constants::CYCLE_WAIT_TIME_MS,
+    disk_columns::{ find_disk_column, DISK_COLUMNS },
+    network_columns::{ find_network_column, NETWORK_COLUMNS },
+    sensor_columns::{ find_sensor_column, SENSOR_COLUMNS },
+    format::UnitMode,
+    graph::GraphStyle,
+    export::{ ExportFormat, SnapshotWriter },
+    process_columns::{ find_column, PROCESS_COLUMNS },
+    process_signal::ProcessSignal,
+    state::{ App, Mode, SortDirection, View },
     utils::{
         delete_previous_character,
         empty_row,
+        format_duration,
+        parse_duration,
+        parse_timestamp,
         navigate_left_input,
         navigate_right_input,
         print_at_end_of_row,
+        print_error_at_end_of_row,
         print_input,
         print_on_last_row,
+        print_on_position,
         enter_input_mode,
         get_terminal_dimensions,
     },
 };
 
-pub fn run_input_mode() {
+/// Printed line by line, raw mode does not return the cursor to the start of a row on newline
+const HELP_LINES: [&str; 52] = [
+    "Accepted commands are:",
+    "",
+    "p - Print running process information",
+    "h - Help",
+    "n - Navigate between columns",
+    "s - Enter search mode for filtering processes via selected column",
+    "    Filters can also use column ids, e.g. name ~ java and (cpu > 20 or mem >= 500Mi)",
+    "    Operators: = != > >= < <= ~ (regex) : (substring), combined with and/or/not",
+    "    The table is filtered while typing, Enter keeps the filter and Esc restores the previous one",
+    "kill [signal] - Send signal (TERM, KILL, HUP, INT, STOP, CONT, USR1, USR2) to selected process",
+    "tree - Toggle process tree view",
+    "cores - Toggle usage and frequency of every CPU core in the header",
+    "net - Toggle total network throughput in the header",
+    "temp - Toggle hottest temperature sensor in the header",
+    "columns [ids] - Set visible columns in given order, lists column ids without arguments",
+    "show <id> - Show column after the selected column",
+    "hide [id] - Hide given or selected column",
+    "sort [id] [asc|desc] - Sort the shown table by column, lists column ids without arguments",
+    "interval [duration] - Set refresh interval, e.g. 500ms, 2s or 1m, shows it without arguments",
+    "view [name] - Switch to processes, performance, disks, network or sensors view, lists views without arguments",
+    "graph <style> - Draw performance graphs with braille or block characters",
+    "window [duration] - Set time span of performance graphs, e.g. 10m, shows it without arguments",
+    "units [iec|si] - Show byte sizes in KiB, MiB or KB, MB, shows the mode without arguments",
+    "export <file> - Write the shown processes to a json, csv or ndjson file, ndjson is appended",
+    "    Exports use a versioned schema, see schema_version in the output",
+    "jump <time> - Jump to a UTC time of the replay, e.g. 03:12:00, 2024-05-01 03:12:00, +1m or -30s",
+    "q - Exit program",
+    "",
+    "In print mode:",
+    "",
+    "Up/Down or j/k - Move row cursor",
+    "PageUp/PageDown, Home/End - Page through processes",
+    "Left/Right - Select column",
+    "r - Reverse sort direction",
+    "x - Send signal to selected process",
+    "Enter - Show details of selected process",
+    "t - Toggle process tree view",
+    "c - Toggle CPU core meters",
+    "N - Toggle network throughput in the header",
+    "T - Toggle hottest sensor in the header",
+    "</> - Move selected column left/right",
+    "Space - Collapse/expand selected process in tree view",
+    "/ - Enter search mode",
+    "Tab - Switch between process table, performance graphs, disks, network and sensors",
+    "+/- - Double/halve refresh interval",
+    "p - Pause/resume refreshing",
+    "",
+    "In replay:",
+    "",
+    "p - Play/pause",
+    "[/] - Step one snapshot back/forward",
+    "+/- - Double/halve playback speed",
+];
+
+/// Prefixes errors of parsing arguments for printing on the input row
+fn to_error(error: String) -> String {
+    return format!("Error: {error}");
+}
+
+/// Replaces visible columns with the given column ids in given order
+fn set_visible_columns(app: &mut App, ids: &[&str]) -> Result<Option<String>, String> {
+    let mut columns = Vec::new();
+    for id in ids.iter().flat_map(|id| id.split(',')).filter(|id| !id.is_empty()) {
+        match find_column(id) {
+            Some(index) => columns.push(index),
+            None => {
+                return Err(format!("Error: Unknown column '{id}'"));
+            }
+        }
+    }
+    if columns.is_empty() {
+        let ids: Vec<_> = PROCESS_COLUMNS.iter()
+            .map(|column| column.id)
+            .collect();
+        return Ok(Some(format!("Columns: {}", ids.join(" "))));
+    }
+    app.visible_columns = columns;
+    app.selected_column = app.selected_column.min(app.visible_columns.len() - 1);
+    return Ok(None);
+}
+
+/// Inserts column after the selected column
+fn show_column(app: &mut App, id: Option<&str>) -> Result<Option<String>, String> {
+    let Some(index) = id.and_then(find_column) else {
+        return Err(String::from("Error: Unknown column, type 'columns' to list them"));
+    };
+    if app.visible_columns.contains(&index) {
+        return Err(format!("Error: Column '{}' is already shown", PROCESS_COLUMNS[index].id));
+    }
+    app.selected_column += 1;
+    app.visible_columns.insert(app.selected_column.min(app.visible_columns.len()), index);
+    app.selected_column = app.selected_column.min(app.visible_columns.len() - 1);
+    return Ok(None);
+}
+
+/// Removes the given column, or the selected column if none is given
+fn hide_column(app: &mut App, id: Option<&str>) -> Result<Option<String>, String> {
+    let position = match id {
+        Some(id) =>
+            find_column(id).and_then(|index| {
+                app.visible_columns.iter().position(|visible| *visible == index)
+            }),
+        None => Some(app.selected_column),
+    };
+    let Some(position) = position else {
+        return Err(String::from("Error: Column is not shown"));
+    };
+    if app.visible_columns.len() == 1 {
+        return Err(String::from("Error: Cannot hide the last column"));
+    }
+    app.visible_columns.remove(position);
+    app.selected_column = app.selected_column.min(app.visible_columns.len() - 1);
+    return Ok(None);
+}
+
+/// Sorts the shown table by the column in the given direction, lists the column ids without
+/// arguments. Hidden process columns are shown.
+fn set_sort(app: &mut App, arguments: &[&str]) -> Result<Option<String>, String> {
+    let direction = match arguments.get(1).map(|order| order.to_lowercase()).as_deref() {
+        None => None,
+        Some("asc") => Some(SortDirection::ASC),
+        Some("desc") => Some(SortDirection::DESC),
+        Some(order) => {
+            return Err(format!("Error: Unknown order '{order}', expected asc or desc"));
+        }
+    };
+    if app.view == View::DISKS {
+        let Some(column) = arguments.first().and_then(|id| find_disk_column(id)) else {
+            let ids: Vec<_> = DISK_COLUMNS.iter()
+                .map(|column| column.id)
+                .collect();
+            return Ok(Some(format!("Columns: {}", ids.join(" "))));
+        };
+        app.disk_sort_column = column;
+        app.disk_sort_direction = direction.unwrap_or(app.disk_sort_direction);
+        return Ok(None);
+    }
+    if app.view == View::NETWORK {
+        let Some(column) = arguments.first().and_then(|id| find_network_column(id)) else {
+            let ids: Vec<_> = NETWORK_COLUMNS.iter()
+                .map(|column| column.id)
+                .collect();
+            return Ok(Some(format!("Columns: {}", ids.join(" "))));
+        };
+        app.network_sort_column = column;
+        app.network_sort_direction = direction.unwrap_or(app.network_sort_direction);
+        return Ok(None);
+    }
+    if app.view == View::SENSORS {
+        let Some(column) = arguments.first().and_then(|id| find_sensor_column(id)) else {
+            let ids: Vec<_> = SENSOR_COLUMNS.iter()
+                .map(|column| column.id)
+                .collect();
+            return Ok(Some(format!("Columns: {}", ids.join(" "))));
+        };
+        app.sensor_sort_column = column;
+        app.sensor_sort_direction = direction.unwrap_or(app.sensor_sort_direction);
+        return Ok(None);
+    }
+    let Some(column) = arguments.first().and_then(|id| find_column(id)) else {
+        let ids: Vec<_> = PROCESS_COLUMNS.iter()
+            .map(|column| column.id)
+            .collect();
+        return Ok(Some(format!("Columns: {}", ids.join(" "))));
+    };
+    app.selected_column = match app.visible_columns.iter().position(|index| *index == column) {
+        Some(position) => position,
+        None => {
+            app.visible_columns.push(column);
+            app.visible_columns.len() - 1
+        }
+    };
+    app.sort_direction = direction.unwrap_or(app.sort_direction);
+    return Ok(None);
+}
+
+/// Sets the sampling interval, shows the current interval without an argument
+fn set_interval(app: &mut App, interval: Option<&str>) -> Result<Option<String>, String> {
+    let Some(interval) = interval else {
+        return Ok(Some(format!("Interval: {}", format_duration(app.sampler.interval()))));
+    };
+    app.sampler.set_interval(parse_interval(interval).map_err(to_error)?);
+    return Ok(None);
+}
+
+/// Switches the view, lists the views without an argument
+fn set_view(app: &mut App, name: Option<&str>) -> Result<Option<String>, String> {
+    let Some(name) = name else {
+        let names: Vec<_> = View::ALL.iter()
+            .map(|view| view.name())
+            .collect();
+        return Ok(Some(format!("Views: {}", names.join(" "))));
+    };
+    let Some(view) = View::from_name(name) else {
+        return Err(format!("Error: Unknown view '{name}'"));
+    };
+    app.view = view;
+    return Ok(None);
+}
+
+fn set_graph_style(app: &mut App, name: Option<&str>) -> Result<Option<String>, String> {
+    let Some(graph_style) = name.and_then(GraphStyle::from_name) else {
+        return Err(String::from("Error: Expected graph style braille or block"));
+    };
+    app.graph_style = graph_style;
+    return Ok(None);
+}
+
+/// Sets the prefixes of byte sizes, shows the current mode without an argument
+fn set_unit_mode(app: &mut App, name: Option<&str>) -> Result<Option<String>, String> {
+    let Some(name) = name else {
+        return Ok(Some(format!("Units: {}", app.unit_mode.name())));
+    };
+    let Some(unit_mode) = UnitMode::from_name(name) else {
+        return Err(String::from("Error: Expected unit mode iec or si"));
+    };
+    app.unit_mode = unit_mode;
+    return Ok(None);
+}
+
+/// Sets the time span of the performance graphs, shows the current span without an argument
+fn set_graph_window(app: &mut App, window: Option<&str>) -> Result<Option<String>, String> {
+    let Some(window) = window else {
+        let window = Duration::from_millis(app.graph_window_ms);
+        return Ok(Some(format!("Window: {}", format_duration(window))));
+    };
+    let window = parse_graph_window(window).map_err(to_error)?;
+    app.graph_window_ms = window.as_millis() as u64;
+    return Ok(None);
+}
+
+/// Writes the current snapshot with the shown processes, the format is picked by the extension
+fn export_snapshot(app: &App, path: &str) -> Result<Option<String>, String> {
+    if path.is_empty() {
+        return Err(String::from("Error: Missing file, e.g. export processes.json"));
+    }
+    let path = Path::new(path);
+    let Some(format) = ExportFormat::from_path(path) else {
+        return Err(String::from("Error: Unknown export format, use a json, csv or ndjson file"));
+    };
+    let Some(snapshot) = &app.snapshot else {
+        return Err(String::from("Error: No snapshot taken yet"));
+    };
+    SnapshotWriter::create(path, format)
+        .and_then(|mut writer| writer.write(snapshot, app))
+        .map_err(to_error)?;
+    return Ok(Some(format!("Exported to {}", path.display())));
+}
+
+/// Jumps the replay to an absolute time or by a duration prefixed with + or -
+fn jump_replay(app: &mut App, time: &str) -> Result<Option<String>, String> {
+    let Some(replay) = &mut app.replay else {
+        return Err(String::from("Error: Only available while replaying a recording"));
+    };
+    let current_ms = replay.current().timestamp_ms;
+    let timestamp_ms = if let Some(offset) = time.strip_prefix('+') {
+        current_ms.saturating_add(parse_duration(offset).map_err(to_error)?.as_millis() as u64)
+    } else if let Some(offset) = time.strip_prefix('-') {
+        current_ms.saturating_sub(parse_duration(offset).map_err(to_error)?.as_millis() as u64)
+    } else {
+        parse_timestamp(time, current_ms / 1000).map_err(to_error)? * 1000
+    };
+    replay.jump_to(timestamp_ms);
+    return Ok(None);
+}
+
+/// Waits for the next event, printing the prompt again when the process is continued after a
+/// suspend. The table is redrawn once input mode is left.
+fn read_input_event(app: &mut App, input: &str, cursor_position: u16, height: u16) -> Event {
+    queue!(stdout(), cursor::MoveTo(cursor_position, height)).unwrap();
+    stdout().flush().expect("failed to flush");
+    while !poll(Duration::from_millis(CYCLE_WAIT_TIME_MS)).is_ok_and(|e| { e }) {
+        if take_resumed(app) {
+            empty_row(height);
+            print_on_last_row(format!(":{input}").as_str());
+            queue!(stdout(), cursor::Show, cursor::MoveTo(cursor_position, height)).unwrap();
+            stdout().flush().expect("failed to flush");
+        }
+    }
+    return read().unwrap();
+}
+
+pub fn run_input_mode(app: &mut App) {
     let height = get_terminal_dimensions().1;
     empty_row(height);
-    unsafe {
-        let input_prefix = ":";
-        let cursor_start_position = enter_input_mode(&input_prefix);
-        let mut input = String::new();
-        let mut cursor_position: u16 = cursor_start_position;
-        let mut cleanup_needed = false;
-        while MODE == Mode::INPUT {
-            queue!(stdout(), cursor::MoveTo(cursor_position, height)).unwrap();
-            stdout().flush().ok().expect("failed to flush");
-            match read().unwrap() {
-                Event::Key(KeyEvent { code: KeyCode::Esc, kind: KeyEventKind::Press, .. }) => {
-                    MODE = Mode::PRINT;
-                }
-                Event::Key(KeyEvent { code: KeyCode::Enter, kind: KeyEventKind::Press, .. }) => {
-                    if input.chars().count() > 0 {
-                        match input.as_str() {
-                            "p" => {
-                                MODE = Mode::PRINT;
-                            }
-                            "q" => {
-                                MODE = Mode::EXIT;
-                            }
-                            "s" => {
-                                MODE = Mode::SEARCH;
-                            }
-                            "h" => {
-                                clearscreen::clear().expect("failed to clear");
-                                queue!(stdout(), cursor::MoveTo(0, 0)).unwrap();
-                                println!("Accepted commands are:\n");
-                                println!("p - Print running process information");
-                                println!("h - Help");
-                                println!("n - Navigate between columns");
-                                println!(
-                                    "s - Enter search mode for filtering processes via selected column"
-                                );
-                                println!("q - Exit program");
+    let input_prefix = ":";
+    let cursor_start_position = enter_input_mode(input_prefix);
+    let mut input = String::new();
+    let mut cursor_position: u16 = cursor_start_position;
+    let mut cleanup_needed = false;
+    while app.mode == Mode::INPUT {
+        let event = read_input_event(app, &input, cursor_position, height);
+        if handle_control_key(app, &event) {
+            continue;
+        }
+        match event {
+            Event::Key(KeyEvent { code: KeyCode::Esc, kind: KeyEventKind::Press, .. }) => {
+                app.mode = Mode::PRINT;
+            }
+            Event::Key(KeyEvent { code: KeyCode::Enter, kind: KeyEventKind::Press, .. }) if
+                input.chars().count() > 0
+            => {
+                let mut words = input.split_whitespace();
+                let command = words.next().unwrap_or_default();
+                let arguments: Vec<&str> = words.collect();
+                let argument = arguments.first().copied();
+                // Result of commands that change the process table
+                let mut result = None;
+                match command {
+                    "p" => {
+                        app.mode = Mode::PRINT;
+                    }
+                    "q" => {
+                        app.mode = Mode::EXIT;
+                    }
+                    "s" => {
+                        app.mode = Mode::SEARCH;
+                    }
+                    "tree" => {
+                        app.tree_view = !app.tree_view;
+                        app.mode = Mode::PRINT;
+                    }
+                    "cores" => {
+                        app.show_cores = !app.show_cores;
+                        app.mode = Mode::PRINT;
+                    }
+                    "net" => {
+                        app.show_network = !app.show_network;
+                        app.mode = Mode::PRINT;
+                    }
+                    "temp" => {
+                        app.show_hottest_sensor = !app.show_hottest_sensor;
+                        app.mode = Mode::PRINT;
+                    }
+                    "columns" => {
+                        result = Some(set_visible_columns(app, &arguments));
+                    }
+                    "show" => {
+                        result = Some(show_column(app, argument));
+                    }
+                    "hide" => {
+                        result = Some(hide_column(app, argument));
+                    }
+                    "sort" => {
+                        result = Some(set_sort(app, &arguments));
+                    }
+                    "interval" => {
+                        result = Some(set_interval(app, argument));
+                    }
+                    "view" => {
+                        result = Some(set_view(app, argument));
+                    }
+                    "graph" => {
+                        result = Some(set_graph_style(app, argument));
+                    }
+                    "window" => {
+                        result = Some(set_graph_window(app, argument));
+                    }
+                    "units" => {
+                        result = Some(set_unit_mode(app, argument));
+                    }
+                    "export" => {
+                        result = Some(export_snapshot(app, input.trim_start()[command.len()..].trim()));
+                    }
+                    "jump" => {
+                        result = Some(jump_replay(app, input.trim_start()[command.len()..].trim()));
+                    }
+                    "kill" if app.replay.is_some() => {
+                        print_error_at_end_of_row(
+                            "Error: Recorded processes cannot be signaled",
+                            height
+                        );
+                        cleanup_needed = true;
+                    }
+                    "kill" => {
+                        match argument.map(ProcessSignal::from_name) {
+                            Some(None) => {
+                                print_error_at_end_of_row("Error: Unknown signal", height);
                                 cleanup_needed = true;
-                                input = String::new();
-                                cursor_position = cursor_start_position;
                             }
-                            _ => {
-                                print_at_end_of_row(
-                                    "Error: Incorrect input, type 'h' for help",
-                                    height
-                                );
-                                cleanup_needed = true;
+                            signal => {
+                                app.pending_signal = signal.flatten();
+                                app.mode = Mode::SIGNAL;
                             }
                         }
                     }
+                    "h" => {
+                        clearscreen::clear().expect("failed to clear");
+                        for (row, line) in HELP_LINES.iter().enumerate() {
+                            print_on_position(line, row as u16, 0);
+                        }
+                        cleanup_needed = true;
+                        input = String::new();
+                        cursor_position = cursor_start_position;
+                    }
+                    _ => {
+                        print_error_at_end_of_row(
+                            "Error: Incorrect input, type 'h' for help",
+                            height
+                        );
+                        cleanup_needed = true;
+                    }
                 }
-                Event::Key(
-                    KeyEvent { code: KeyCode::Backspace, kind: KeyEventKind::Press, .. },
-                ) => {
-                    cursor_position = delete_previous_character(
-                        input_prefix,
-                        &mut input,
-                        height,
-                        cursor_position,
-                        cursor_start_position
-                    );
-                }
-                Event::Key(KeyEvent { code: KeyCode::Left, kind: KeyEventKind::Press, .. }) => {
-                    cursor_position = navigate_left_input(
-                        height,
-                        cursor_position,
-                        cursor_start_position
-                    );
-                }
-                Event::Key(KeyEvent { code: KeyCode::Right, kind: KeyEventKind::Press, .. }) => {
-                    cursor_position = navigate_right_input(
-                        &input,
-                        height,
-                        cursor_position,
-                        cursor_start_position
-                    );
-                }
-                Event::Key(KeyEvent { code, kind: KeyEventKind::Press, .. }) => {
-                    cursor_position = print_input(
-                        &mut input,
-                        code,
-                        height,
-                        cursor_position,
-                        cursor_start_position
-                    );
-                    if cleanup_needed {
+                match result {
+                    Some(Ok(None)) => {
+                        clearscreen::clear().expect("failed to clear");
+                        app.mode = Mode::PRINT;
+                    }
+                    Some(Ok(Some(info))) => {
+                        empty_row(height);
+                        print_on_last_row(format!(":{input}").as_str());
+                        print_at_end_of_row(info.as_str(), height);
+                        cleanup_needed = true;
+                    }
+                    Some(Err(error)) => {
                         empty_row(height);
                         print_on_last_row(format!(":{input}").as_str());
-                        cleanup_needed = false;
+                        print_error_at_end_of_row(error.as_str(), height);
+                        cleanup_needed = true;
                     }
+                    None => (),
+                }
+            }
+            Event::Key(
+                KeyEvent { code: KeyCode::Backspace, kind: KeyEventKind::Press, .. },
+            ) => {
+                cursor_position = delete_previous_character(
+                    input_prefix,
+                    &mut input,
+                    height,
+                    cursor_position,
+                    cursor_start_position
+                );
+            }
+            Event::Key(KeyEvent { code: KeyCode::Left, kind: KeyEventKind::Press, .. }) => {
+                cursor_position = navigate_left_input(
+                    height,
+                    cursor_position,
+                    cursor_start_position
+                );
+            }
+            Event::Key(KeyEvent { code: KeyCode::Right, kind: KeyEventKind::Press, .. }) => {
+                cursor_position = navigate_right_input(
+                    &input,
+                    height,
+                    cursor_position,
+                    cursor_start_position
+                );
+            }
+            Event::Key(KeyEvent { code, kind: KeyEventKind::Press, .. }) => {
+                cursor_position = print_input(
+                    &mut input,
+                    code,
+                    height,
+                    cursor_position,
+                    cursor_start_position
+                );
+                if cleanup_needed {
+                    empty_row(height);
+                    print_on_last_row(format!(":{input}").as_str());
+                    cleanup_needed = false;
                 }
-                _ => (),
             }
+            _ => (),
         }
     }
     empty_row(height);
@@ -1,22 +1,71 @@
+mod detail;
 mod print;
 mod input;
 mod search;
+mod signal;
 
-use crate::state::{ Mode, MODE };
+use crossterm::event::{ Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers };
 
-pub fn run() {
-    unsafe {
-        match MODE {
-            Mode::PRINT => {
-                print::run_print_mode();
-            }
-            Mode::INPUT => {
-                input::run_input_mode();
-            }
-            Mode::SEARCH => {
-                search::run_search_mode();
-            }
-            Mode::EXIT => (),
+use crate::{ state::{ App, Dimensions, Mode }, terminal::{ self, suspend } };
+
+/// Handles Ctrl-C and Ctrl-Z, which raw mode keeps from raising signals, before any mode
+/// handles the event. Returns true if the event was one of them.
+fn handle_control_key(app: &mut App, event: &Event) -> bool {
+    match event {
+        Event::Key(
+            KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press,
+                ..
+            },
+        ) => {
+            app.mode = Mode::EXIT;
+        }
+        Event::Key(
+            KeyEvent {
+                code: KeyCode::Char('z'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press,
+                ..
+            },
+        ) => {
+            suspend();
+        }
+        _ => {
+            return false;
+        }
+    }
+    return true;
+}
+
+/// Returns true once after the process was continued from a suspend.
+/// The screen was cleared, so the next print clears and redraws all of it.
+fn take_resumed(app: &mut App) -> bool {
+    if !terminal::take_resumed() {
+        return false;
+    }
+    app.previous_dimensions = Dimensions { width: 0, height: 0 };
+    return true;
+}
+
+pub fn run(app: &mut App) {
+    match app.mode {
+        Mode::PRINT => {
+            print::run_print_mode(app);
+        }
+        Mode::INPUT => {
+            input::run_input_mode(app);
+        }
+        Mode::SEARCH => {
+            search::run_search_mode(app);
+        }
+        Mode::SIGNAL => {
+            signal::run_signal_mode(app);
+        }
+        Mode::DETAIL => {
+            detail::run_detail_mode(app);
         }
+        Mode::EXIT => (),
     }
 }
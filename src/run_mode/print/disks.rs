@@ -0,0 +1,74 @@
+use super::process_table::{ print_sorted_table, TableRow };
+use crate::{
+    disk_columns::{ DiskRow, DISK_COLUMNS },
+    snapshot::Snapshot,
+    state::App,
+};
+
+/// Disks of the snapshot sorted by the disk sort column
+fn get_disk_rows<'a>(snapshot: &'a Snapshot, app: &App) -> Vec<DiskRow<'a>> {
+    let mut rows: Vec<DiskRow> = snapshot.disks
+        .iter()
+        .map(|disk| DiskRow { disk, rates: app.history.get_disk_rates(&disk.mount_point) })
+        .collect();
+    let column = &DISK_COLUMNS[app.disk_sort_column];
+    rows.sort_by(|a, b| app.disk_sort_direction.apply(column.compare(a, b)));
+    return rows;
+}
+
+/// Prints the mounted filesystems as a table sorted like the process table
+pub fn print_disks(start_row: u16, snapshot: &Snapshot, app: &App) {
+    let headers: Vec<_> = DISK_COLUMNS.iter()
+        .map(|column| (column.header, column.alignment))
+        .collect();
+    let rows: Vec<TableRow> = get_disk_rows(snapshot, app)
+        .iter()
+        .map(|row| {
+            return TableRow {
+                cells: DISK_COLUMNS.iter()
+                    .map(|column| (column.value)(row, app.unit_mode))
+                    .collect(),
+                color: None,
+            };
+        })
+        .collect();
+    print_sorted_table(start_row, &headers, app.disk_sort_column, app.disk_sort_direction, &rows);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::process_table::tests::assert_table_fits;
+    use crate::{ format::UnitMode, history::DiskRates, snapshot::DiskSnapshot };
+    use super::*;
+
+    #[test]
+    fn narrow_disk_table_fits() {
+        let disk = DiskSnapshot {
+            name: String::from("/dev/nvme0n1p2"),
+            mount_point: String::from("/mnt/a/rather/long/mount/point"),
+            file_system: String::from("ext4"),
+            total_space: 2_000_398_934_016,
+            available_space: 731_201_536_000,
+            total_read_bytes: Some(91_300_000_000),
+            total_written_bytes: None,
+        };
+        let rows = [
+            DiskRow { disk: &disk, rates: Some(DiskRates { read: 1.5e9, written: 12_345.0 }) },
+            DiskRow { disk: &disk, rates: None },
+        ];
+        let headers: Vec<_> = DISK_COLUMNS.iter()
+            .map(|column| (column.header, column.alignment))
+            .collect();
+        let cells: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| {
+                return DISK_COLUMNS.iter()
+                    .map(|column| (column.value)(row, UnitMode::IEC))
+                    .collect();
+            })
+            .collect();
+        for width in 0..=160 {
+            assert_table_fits(&headers, &cells, width);
+        }
+    }
+}
@@ -1,70 +1,362 @@
+mod disks;
+mod network;
+mod performance;
+mod sensors;
 mod resource_header;
 mod process_table;
 
-use std::time::Duration;
+use std::{ io::{ stdout, Write }, sync::Arc, time::Duration };
 
-use crossterm::event::{ poll, read, Event, KeyCode, KeyEvent, KeyEventKind };
+use crossterm::{
+    cursor,
+    event::{ poll, read, Event, KeyCode, KeyEvent, KeyEventKind },
+    queue,
+};
+use disks::print_disks;
+use network::print_network;
+use performance::print_performance;
+use sensors::print_sensors;
 use process_table::print_processes;
-use resource_header::print_resource_header;
-use sysinfo::{ CpuRefreshKind, RefreshKind, System };
+use resource_header::{ print_refresh_status, print_replay_status, print_resource_header };
 
+use super::{
+    handle_control_key,
+    search::{ handle_search_input, print_search_row, start_search_session },
+    take_resumed,
+};
 use crate::{
-    constants::{ CYCLE_WAIT_TIME_MS, PROCESS_HEADERS_LEN },
-    state::{ Mode, MODE, SELECTED_COLUMN, SORT_DIRECTION },
+    constants::{ INPUT_POLL_TIME_MS, MAX_SAMPLE_INTERVAL_MS, MIN_SAMPLE_INTERVAL_MS },
+    disk_columns::DISK_COLUMNS,
+    network_columns::NETWORK_COLUMNS,
+    sensor_columns::SENSOR_COLUMNS,
+    state::{ App, Mode, SortDirection, View },
     utils::clear_screen_on_dimension_changed,
 };
 
-fn refresh_system_usage(sys: &mut System) {
-    std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
-    sys.refresh_all();
-    sys.refresh_cpu_usage();
+/// Collapses or expands the subtree of the selected process in tree view
+fn toggle_selected_collapsed(app: &mut App) {
+    if let Some(selected) = &app.selected_process {
+        match app.collapsed_pids.iter().position(|collapsed| *collapsed == selected.pid) {
+            Some(index) => {
+                app.collapsed_pids.remove(index);
+            }
+            None => app.collapsed_pids.push(selected.pid),
+        }
+    }
+}
+
+/// Multiplies the sampling interval, keeping it within the supported range
+fn scale_interval(app: &mut App, factor: f64) {
+    let interval_ms = ((app.sampler.interval().as_millis() as f64) * factor).round() as u64;
+    app.sampler.set_interval(
+        Duration::from_millis(interval_ms.clamp(MIN_SAMPLE_INTERVAL_MS, MAX_SAMPLE_INTERVAL_MS))
+    );
+}
+
+/// Sort column, sort direction and column count of the table shown by the disk, network or
+/// sensor view, None for views sorted like the process table or not sorted
+fn get_view_sort(app: &mut App) -> Option<(&mut usize, &mut SortDirection, usize)> {
+    return match app.view {
+        View::DISKS => {
+            Some((&mut app.disk_sort_column, &mut app.disk_sort_direction, DISK_COLUMNS.len()))
+        }
+        View::NETWORK => {
+            Some((
+                &mut app.network_sort_column,
+                &mut app.network_sort_direction,
+                NETWORK_COLUMNS.len(),
+            ))
+        }
+        View::SENSORS => {
+            Some((
+                &mut app.sensor_sort_column,
+                &mut app.sensor_sort_direction,
+                SENSOR_COLUMNS.len(),
+            ))
+        }
+        View::PROCESSES | View::PERFORMANCE => None,
+    };
 }
 
-fn read_user_input() {
-    if poll(Duration::from_millis(CYCLE_WAIT_TIME_MS)).is_ok_and(|e| { e }) {
-        let input = read().unwrap();
-        unsafe {
-            match input {
-                Event::Key(KeyEvent { code: KeyCode::Left, kind: KeyEventKind::Press, .. }) => {
-                    if SELECTED_COLUMN > 0 {
-                        SELECTED_COLUMN -= 1;
-                    }
-                }
-                Event::Key(KeyEvent { code: KeyCode::Right, kind: KeyEventKind::Press, .. }) => {
-                    if SELECTED_COLUMN < PROCESS_HEADERS_LEN - 1 {
-                        SELECTED_COLUMN += 1;
-                    }
-                }
-                Event::Key(KeyEvent { code: KeyCode::Up, kind: KeyEventKind::Press, .. }) => {
-                    SORT_DIRECTION = "ASC";
-                }
-                Event::Key(KeyEvent { code: KeyCode::Down, kind: KeyEventKind::Press, .. }) => {
-                    SORT_DIRECTION = "DESC";
-                }
-                Event::Key(
-                    KeyEvent { code: KeyCode::Char(':'), kind: KeyEventKind::Press, .. },
-                ) => {
-                    MODE = Mode::INPUT;
-                }
-                _ => (),
+/// Handles the playback keys while replaying, returns false for keys handled like in live mode
+fn handle_replay_input(app: &mut App, input: &Event) -> bool {
+    let Some(replay) = &mut app.replay else {
+        return false;
+    };
+    let Event::Key(KeyEvent { code: KeyCode::Char(key), kind: KeyEventKind::Press, .. }) = input else {
+        return false;
+    };
+    match key {
+        'p' => replay.toggle_playing(),
+        '[' => replay.step(-1),
+        ']' => replay.step(1),
+        '+' => replay.scale_speed(2.0),
+        '-' => replay.scale_speed(0.5),
+        // Recorded processes are not running here, so they cannot be signaled or inspected
+        'x' => (),
+        _ => {
+            return false;
+        }
+    }
+    return true;
+}
+
+fn handle_user_input(app: &mut App, input: Event) {
+    if handle_replay_input(app, &input) {
+        return;
+    }
+    match input {
+        Event::Key(KeyEvent { code: KeyCode::Left, kind: KeyEventKind::Press, .. }) if
+            matches!(app.view, View::DISKS | View::NETWORK | View::SENSORS)
+        => {
+            if let Some((column, _, _)) = get_view_sort(app) {
+                *column = column.saturating_sub(1);
             }
         }
+        Event::Key(KeyEvent { code: KeyCode::Right, kind: KeyEventKind::Press, .. }) if
+            matches!(app.view, View::DISKS | View::NETWORK | View::SENSORS)
+        => {
+            if let Some((column, _, column_count)) = get_view_sort(app) {
+                *column = (*column + 1).min(column_count - 1);
+            }
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('r'), kind: KeyEventKind::Press, .. },
+        ) if matches!(app.view, View::DISKS | View::NETWORK | View::SENSORS) => {
+            if let Some((_, direction, _)) = get_view_sort(app) {
+                *direction = direction.reverse();
+            }
+        }
+        Event::Key(KeyEvent { code: KeyCode::Left, kind: KeyEventKind::Press, .. }) => {
+            app.selected_column = app.selected_column.saturating_sub(1);
+        }
+        Event::Key(KeyEvent { code: KeyCode::Right, kind: KeyEventKind::Press, .. }) if
+            app.selected_column < app.visible_columns.len() - 1
+        => {
+            app.selected_column += 1;
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('<'), kind: KeyEventKind::Press, .. },
+        ) if app.selected_column > 0 => {
+            app.visible_columns.swap(app.selected_column, app.selected_column - 1);
+            app.selected_column -= 1;
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('>'), kind: KeyEventKind::Press, .. },
+        ) if app.selected_column < app.visible_columns.len() - 1 => {
+            app.visible_columns.swap(app.selected_column, app.selected_column + 1);
+            app.selected_column += 1;
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('r'), kind: KeyEventKind::Press, .. },
+        ) => {
+            app.sort_direction = app.sort_direction.reverse();
+        }
+        Event::Key(
+            KeyEvent {
+                code: KeyCode::Up | KeyCode::Char('k'),
+                kind: KeyEventKind::Press,
+                ..
+            },
+        ) => {
+            app.selected_row = app.selected_row.saturating_sub(1);
+        }
+        Event::Key(
+            KeyEvent {
+                code: KeyCode::Down | KeyCode::Char('j'),
+                kind: KeyEventKind::Press,
+                ..
+            },
+        ) => {
+            app.selected_row = app.selected_row.saturating_add(1);
+        }
+        Event::Key(KeyEvent { code: KeyCode::PageUp, kind: KeyEventKind::Press, .. }) => {
+            app.selected_row = app.selected_row.saturating_sub(app.process_page_size);
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::PageDown, kind: KeyEventKind::Press, .. },
+        ) => {
+            app.selected_row = app.selected_row.saturating_add(app.process_page_size);
+        }
+        Event::Key(KeyEvent { code: KeyCode::Home, kind: KeyEventKind::Press, .. }) => {
+            app.selected_row = 0;
+        }
+        Event::Key(KeyEvent { code: KeyCode::End, kind: KeyEventKind::Press, .. }) => {
+            // Clamped to the last process when printing
+            app.selected_row = usize::MAX;
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('x'), kind: KeyEventKind::Press, .. },
+        ) if app.view == View::PROCESSES => {
+            app.mode = Mode::SIGNAL;
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('t'), kind: KeyEventKind::Press, .. },
+        ) => {
+            app.tree_view = !app.tree_view;
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('c'), kind: KeyEventKind::Press, .. },
+        ) => {
+            app.show_cores = !app.show_cores;
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('N'), kind: KeyEventKind::Press, .. },
+        ) => {
+            app.show_network = !app.show_network;
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('T'), kind: KeyEventKind::Press, .. },
+        ) => {
+            app.show_hottest_sensor = !app.show_hottest_sensor;
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('+'), kind: KeyEventKind::Press, .. },
+        ) => {
+            scale_interval(app, 2.0);
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('-'), kind: KeyEventKind::Press, .. },
+        ) => {
+            scale_interval(app, 0.5);
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('p'), kind: KeyEventKind::Press, .. },
+        ) => {
+            app.paused = !app.paused;
+            if !app.paused {
+                app.sampler.refresh();
+            }
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char(' '), kind: KeyEventKind::Press, .. },
+        ) if app.tree_view && app.view == View::PROCESSES => {
+            toggle_selected_collapsed(app);
+        }
+        Event::Key(KeyEvent { code: KeyCode::Enter, kind: KeyEventKind::Press, .. }) if
+            app.replay.is_none() && app.view == View::PROCESSES
+        => {
+            app.mode = Mode::DETAIL;
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char(':'), kind: KeyEventKind::Press, .. },
+        ) => {
+            app.mode = Mode::INPUT;
+        }
+        Event::Key(
+            KeyEvent { code: KeyCode::Char('/'), kind: KeyEventKind::Press, .. },
+        ) => {
+            start_search_session(app);
+        }
+        Event::Key(KeyEvent { code: KeyCode::Tab, kind: KeyEventKind::Press, .. }) => {
+            app.view = app.view.next();
+            clearscreen::clear().expect("failed to clear");
+        }
+        _ => (),
     }
 }
 
-pub fn run_print_mode() {
-    // TODO declaring this only once might give incorrect cpu information
-    let mut sys = System::new_with_specifics(
-        RefreshKind::new().with_cpu(CpuRefreshKind::everything())
-    );
-    unsafe {
-        while MODE == Mode::PRINT {
-            clear_screen_on_dimension_changed();
-            refresh_system_usage(&mut sys);
-            let next_row = print_resource_header(&mut sys, 0);
-            print_processes(next_row + 1, &mut sys);
-            read_user_input();
-            std::thread::sleep(std::time::Duration::from_millis(CYCLE_WAIT_TIME_MS));
+fn is_print_mode(app: &App) -> bool {
+    return app.mode == Mode::PRINT || app.mode == Mode::SEARCH;
+}
+
+/// Replaces the snapshot with the latest one published by the sampler or reached by the replay,
+/// returns true if there was one
+fn receive_snapshot(app: &mut App) -> bool {
+    if let Some(replay) = &mut app.replay {
+        replay.advance();
+        let snapshot = replay.current();
+        // Stepping or jumping changes the position without advancing
+        if app.snapshot.as_ref().is_some_and(|shown| Arc::ptr_eq(shown, &snapshot)) {
+            return false;
+        }
+        app.show_snapshot(snapshot);
+        return true;
+    }
+    if app.snapshot.is_none() {
+        // Nothing can be printed before the first snapshot
+        let snapshot = app.sampler.snapshots.recv().expect("sampler thread stopped");
+        app.show_snapshot(snapshot);
+        return true;
+    }
+    if app.paused {
+        return false;
+    }
+    let Some(snapshot) = app.sampler.snapshots.try_iter().last() else {
+        return false;
+    };
+    app.show_snapshot(snapshot);
+    return true;
+}
+
+/// Prints the resource header and process table of the latest snapshot.
+/// header_end is the row the header ended on when last printed.
+fn print_screen(app: &mut App, header_end: &mut u16) {
+    let Some(snapshot) = app.snapshot.clone() else {
+        return;
+    };
+    clear_screen_on_dimension_changed(&mut app.previous_dimensions);
+    queue!(stdout(), cursor::Hide).unwrap();
+    let next_row = print_resource_header(&snapshot, app, 1);
+    if next_row != *header_end {
+        // Rows of the previous layout would stay on screen, e.g. when the core grid is hidden
+        clearscreen::clear().expect("failed to clear");
+        print_resource_header(&snapshot, app, 1);
+        *header_end = next_row;
+    }
+    match &app.replay {
+        Some(replay) => print_replay_status(0, replay),
+        None => print_refresh_status(0, app.sampler.interval(), app.paused),
+    }
+    match app.view {
+        View::PROCESSES => print_processes(next_row + 1, &snapshot, app),
+        View::PERFORMANCE => print_performance(next_row + 1, app),
+        View::DISKS => print_disks(next_row + 1, &snapshot, app),
+        View::NETWORK => print_network(next_row + 1, &snapshot, app),
+        View::SENSORS => print_sensors(next_row + 1, &snapshot, app),
+    }
+    print_search_row(app);
+    stdout().flush().expect("failed to flush");
+}
+
+/// Handles an input event, returns true if the screen needs to be reprinted
+fn handle_event(app: &mut App, input: Event) -> bool {
+    if handle_control_key(app, &input) {
+        return true;
+    }
+    if app.mode == Mode::SEARCH {
+        return handle_search_input(app, input);
+    }
+    handle_user_input(app, input);
+    return true;
+}
+
+/// Handles all pending input events, waiting up to INPUT_POLL_TIME_MS for the first one.
+/// Returns true if the screen needs to be reprinted.
+fn read_user_input(app: &mut App) -> bool {
+    let mut redraw = false;
+    let mut timeout = Duration::from_millis(INPUT_POLL_TIME_MS);
+    while poll(timeout).is_ok_and(|e| { e }) {
+        redraw |= handle_event(app, read().unwrap());
+        // Leave remaining events to the mode that was switched to
+        if !is_print_mode(app) {
+            break;
+        }
+        timeout = Duration::ZERO;
+    }
+    return redraw;
+}
+
+/// Reprints the screen whenever the sampler publishes a snapshot or input changes what is shown
+pub fn run_print_mode(app: &mut App) {
+    let mut header_end = 0;
+    let mut redraw = true;
+    while is_print_mode(app) {
+        redraw |= receive_snapshot(app);
+        redraw |= take_resumed(app);
+        if redraw {
+            print_screen(app, &mut header_end);
         }
+        redraw = read_user_input(app);
     }
 }
@@ -0,0 +1,76 @@
+use super::process_table::{ get_column_width, print_sorted_table, TableRow };
+use crate::{
+    network_columns::{ NetworkRow, NETWORK_COLUMNS },
+    snapshot::Snapshot,
+    state::App,
+};
+
+/// Interfaces of the snapshot sorted by the network sort column
+fn get_network_rows<'a>(snapshot: &'a Snapshot, app: &'a App) -> Vec<NetworkRow<'a>> {
+    let mut rows: Vec<NetworkRow> = snapshot.networks
+        .iter()
+        .map(|network| NetworkRow { network, history: app.history.get_network(&network.name) })
+        .collect();
+    let column = &NETWORK_COLUMNS[app.network_sort_column];
+    rows.sort_by(|a, b| app.network_sort_direction.apply(column.compare(a, b)));
+    return rows;
+}
+
+/// Prints the network interfaces as a table sorted like the process table
+pub fn print_network(start_row: u16, snapshot: &Snapshot, app: &App) {
+    let headers: Vec<_> = NETWORK_COLUMNS.iter()
+        .map(|column| (column.header, column.alignment))
+        .collect();
+    // Leaves room for the separator and a space on both sides
+    let graph_width = (get_column_width(NETWORK_COLUMNS.len()) as usize).saturating_sub(3);
+    let rows: Vec<TableRow> = get_network_rows(snapshot, app)
+        .iter()
+        .map(|row| {
+            return TableRow {
+                cells: NETWORK_COLUMNS.iter()
+                    .map(|column| column.format(row, graph_width, app.unit_mode))
+                    .collect(),
+                color: None,
+            };
+        })
+        .collect();
+    print_sorted_table(
+        start_row,
+        &headers,
+        app.network_sort_column,
+        app.network_sort_direction,
+        &rows
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::process_table::tests::assert_table_fits;
+    use crate::{ format::UnitMode, snapshot::NetworkSnapshot };
+    use super::*;
+
+    #[test]
+    fn narrow_network_table_fits() {
+        let network = NetworkSnapshot {
+            name: String::from("enp0s31f6-with-a-long-name"),
+            total_received: 98_765_432_109,
+            total_transmitted: 1_234_567,
+            total_packets_received: 81_234_567,
+            total_packets_transmitted: 912_345,
+            total_errors_received: 12,
+            total_errors_transmitted: 0,
+        };
+        let row = NetworkRow { network: &network, history: None };
+        let headers: Vec<_> = NETWORK_COLUMNS.iter()
+            .map(|column| (column.header, column.alignment))
+            .collect();
+        let cells = [
+            NETWORK_COLUMNS.iter()
+                .map(|column| column.format(&row, 20, UnitMode::SI))
+                .collect(),
+        ];
+        for width in 0..=160 {
+            assert_table_fits(&headers, &cells, width);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::{ io::stdout, time::Duration };
+
+use colored::{ Color, Colorize };
+use crossterm::{ cursor, queue };
+
+use crate::{
+    graph::{ format_graph, sample_window },
+    history::SystemSample,
+    state::App,
+    utils::{ empty_row, format_duration, get_terminal_dimensions },
+};
+
+/// Value graphed over time, as a fraction between 0 and 1
+struct PerformanceGraph {
+    title: String,
+    color: Color,
+    series: Vec<(u64, f64)>,
+}
+
+fn get_graphs(app: &App, last: &SystemSample) -> Vec<PerformanceGraph> {
+    let history = &app.history;
+    let mut graphs = vec![
+        PerformanceGraph {
+            title: format!("CPU {:.1}%", last.cpu * 100.0),
+            color: Color::Green,
+            series: history.get_system_series(|sample| Some(sample.cpu)),
+        },
+        PerformanceGraph {
+            title: format!("Memory {:.1}%", last.memory * 100.0),
+            color: Color::Cyan,
+            series: history.get_system_series(|sample| Some(sample.memory)),
+        },
+        PerformanceGraph {
+            title: match last.swap {
+                Some(swap) => format!("Swap {:.1}%", swap * 100.0),
+                None => String::from("Swap not configured"),
+            },
+            color: Color::Yellow,
+            series: history.get_system_series(|sample| sample.swap),
+        },
+    ];
+    for (i, usage) in last.gpus.iter().flatten().enumerate() {
+        graphs.push(PerformanceGraph {
+            title: match usage {
+                Some(usage) => format!("GPU {} {:.1}%", i, usage * 100.0),
+                None => format!("GPU {} unavailable", i),
+            },
+            color: Color::Magenta,
+            series: history.get_system_series(|sample| {
+                return sample.gpus.as_ref().and_then(|gpus| gpus.get(i).copied().flatten());
+            }),
+        });
+    }
+    return graphs;
+}
+
+/// Prints a title row and the graph rows, with the scale on the right side
+fn print_graph(row: u16, rows: u16, graph: &PerformanceGraph, app: &App, end_ms: u64) {
+    let width = get_terminal_dimensions().0 as usize;
+    let window = format!("last {}", format_duration(Duration::from_millis(app.graph_window_ms)));
+    empty_row(row);
+    queue!(stdout(), cursor::MoveTo(0, row)).unwrap();
+    let padding = width.saturating_sub(graph.title.chars().count() + window.chars().count());
+    print!("{}{}{}", graph.title.as_str().bold(), " ".repeat(padding), window);
+
+    let scale_width = 5;
+    let graph_width = width.saturating_sub(scale_width);
+    let points = sample_window(
+        &graph.series,
+        end_ms,
+        app.graph_window_ms,
+        graph_width * app.graph_style.points_per_column()
+    );
+    let lines = format_graph(&points, rows as usize, app.graph_style);
+    for (i, line) in lines.iter().enumerate() {
+        let scale = match i {
+            0 => "100%",
+            i if i == lines.len() - 1 => "0%",
+            _ => "",
+        };
+        queue!(stdout(), cursor::MoveTo(0, row + 1 + (i as u16))).unwrap();
+        print!("{}{:>scale_width$}", line.color(graph.color), scale);
+    }
+}
+
+/// Prints graphs of CPU, memory, swap and GPU usage over the graph window below start_row,
+/// sharing the rows evenly. Graphs that do not fit at least one graph row are left out.
+pub fn print_performance(start_row: u16, app: &App) {
+    let height = get_terminal_dimensions().1;
+    let Some(last) = app.history.last_system_sample() else {
+        return;
+    };
+    let graphs = get_graphs(app, last);
+    let available = (height - 1).saturating_sub(start_row);
+    let shown = graphs.len().min((available / 2) as usize);
+    if shown == 0 {
+        return;
+    }
+    let rows_per_graph = available / (shown as u16);
+    for (i, graph) in graphs.iter().take(shown).enumerate() {
+        let row = start_row + (i as u16) * rows_per_graph;
+        print_graph(row, rows_per_graph - 1, graph, app, last.timestamp_ms);
+    }
+    // Rows left over by the even split
+    for row in start_row + (shown as u16) * rows_per_graph..height - 1 {
+        empty_row(row);
+    }
+}
@@ -1,12 +1,14 @@
 use std::io::stdout;
-use colored::Colorize;
+use colored::{ Color, Colorize };
 use crossterm::{ cursor, queue };
 use regex::Regex;
-use sysinfo::{ Pid, Process, System };
 
 use crate::{
-    constants::{ COLUMN_SEPARATOR, PROCESS_HEADERS, PROCESS_HEADERS_LEN },
-    state::{ SEARCH_TEXT, SELECTED_COLUMN, SORT_DIRECTION },
+    constants::COLUMN_SEPARATOR,
+    process_columns::{ Alignment, PROCESS_COLUMNS },
+    process_list::get_process_rows,
+    snapshot::Snapshot,
+    state::{ App, SelectedProcess, SortDirection },
     utils::{ get_terminal_dimensions, strip_closing_quotes, truncate_text },
 };
 
@@ -18,179 +20,491 @@ fn print_row_separator(row: u16) {
     }
 }
 
-fn print_column(row: u16, col_index: usize, col_width: u16, cols_length: usize, text: &str) {
-    let col = col_width * (col_index as u16);
-    queue!(stdout(), cursor::MoveTo(col, row)).unwrap();
-    let is_selected = get_is_selected(col_index);
-    let mut stripped_text = strip_closing_quotes(text);
-    // Checking length of color formatted text does not work, since it is ANSII encoded
-    let mut col_print_len = format!("{}{}", COLUMN_SEPARATOR, stripped_text).chars().count() as u16;
-    if col_print_len > col_width - 1 {
-        stripped_text = truncate_text(stripped_text, col_width - 4);
-        col_print_len = format!("{}{}", COLUMN_SEPARATOR, stripped_text).chars().count() as u16;
-    }
-    let col_start = format!(
-        "{}{}",
-        COLUMN_SEPARATOR.on_white(),
-        format_selected_color(format!(" {}", stripped_text).as_str(), is_selected)
-    );
-    print!("{}", col_start);
+struct CellStyle<'a> {
+    alignment: Alignment,
+    is_selected: bool,
+    is_selected_row: bool,
+    /// Patterns whose matches are highlighted in the cell
+    highlights: &'a [&'a Regex],
+    /// Text color used instead of the highlights outside the row cursor
+    color: Option<Color>,
+}
 
-    let mut white_spaces = col_width - col_print_len;
-    let is_last = col_index == cols_length - 1;
-    let width = get_terminal_dimensions().0;
+/// Narrowest column that fits the separator, a truncated header and the sort arrow
+const MIN_COLUMN_WIDTH: u16 = 6;
 
-    if is_last {
-        white_spaces = width - col - col_print_len - 1;
+/// Position of a cell in a table whose columns share the terminal width
+#[derive(Clone, Copy)]
+struct CellLayout {
+    col_index: usize,
+    col_width: u16,
+    cols_length: usize,
+    /// Terminal width, the last column takes the space left by the others
+    width: u16,
+}
+
+impl CellLayout {
+    fn column(&self) -> u16 {
+        return self.col_width.saturating_mul(self.col_index as u16);
+    }
+
+    fn is_last(&self) -> bool {
+        return self.col_index + 1 == self.cols_length;
     }
 
-    for _ in 0..white_spaces {
-        print!("{}", format_selected_color(" ", is_selected));
+    /// Characters from the separator before the cell up to the next separator
+    fn cell_width(&self) -> u16 {
+        if self.is_last() {
+            return self.width.saturating_sub(self.column());
+        }
+        return self.col_width;
+    }
+}
+
+/// Number of columns that fit the width and the width of each, columns past them are not shown
+fn get_column_layout(column_count: usize, width: u16) -> (usize, u16) {
+    let shown = column_count.min(((width / MIN_COLUMN_WIDTH) as usize).max(1));
+    return (shown, width / (shown.max(1) as u16));
+}
+
+/// Characters left for the text of a cell after the separator, the space before the text
+/// and the space kept after right aligned text
+fn get_text_width(layout: &CellLayout, alignment: Alignment) -> usize {
+    let trailing_space = if alignment == Alignment::RIGHT { 1 } else { 0 };
+    return (layout.cell_width() as usize).saturating_sub(
+        COLUMN_SEPARATOR.chars().count() + 1 + trailing_space
+    );
+}
+
+/// Cell text cut to the column width and the number of padding spaces printed with it
+fn fit_cell_text(text: &str, layout: &CellLayout, alignment: Alignment) -> (String, u16) {
+    let text_width = get_text_width(layout, alignment);
+    // Checking length of color formatted text does not work, since it is ANSII encoded
+    let stripped_text = truncate_text(strip_closing_quotes(text), text_width as u16);
+    let white_spaces = text_width.saturating_sub(stripped_text.chars().count());
+    return (stripped_text, white_spaces as u16);
+}
+
+fn print_column(row: u16, layout: CellLayout, text: &str, style: CellStyle) {
+    let is_selected = style.is_selected;
+    let is_selected_row = style.is_selected_row;
+    queue!(stdout(), cursor::MoveTo(layout.column(), row)).unwrap();
+    let (stripped_text, white_spaces) = fit_cell_text(text, &layout, style.alignment);
+
+    let padding = format_selected_color(
+        " ".repeat(white_spaces as usize).as_str(),
+        is_selected,
+        is_selected_row
+    );
+    let cell_text = match style.color {
+        Some(color) if !is_selected_row && is_selected => {
+            stripped_text.as_str().color(color).on_blue().to_string()
+        }
+        Some(color) if !is_selected_row => stripped_text.as_str().color(color).to_string(),
+        _ => format_highlighted_text(&stripped_text, is_selected, is_selected_row, style.highlights),
+    };
+    let text = format!("{}{}", format_selected_color(" ", is_selected, is_selected_row), cell_text);
+    print!("{}", COLUMN_SEPARATOR.on_white());
+    if style.alignment == Alignment::RIGHT {
+        // Right aligned text keeps one space before the next separator
+        print!("{}{}{}", padding, text, format_selected_color(" ", is_selected, is_selected_row));
+    } else {
+        print!("{}{}", text, padding);
     }
 
-    if is_last {
-        queue!(stdout(), cursor::MoveTo(width, row)).unwrap();
+    if layout.is_last() {
+        queue!(stdout(), cursor::MoveTo(layout.width, row)).unwrap();
         print!("{}", COLUMN_SEPARATOR.on_white());
     }
 }
 
-fn format_selected_color(text: &str, is_selected: bool) -> String {
+fn format_selected_color(text: &str, is_selected: bool, is_selected_row: bool) -> String {
+    if is_selected_row {
+        return text.black().on_white().to_string();
+    }
     if !is_selected {
-        return format!("{}", text);
+        return text.to_string();
     }
-    return format!("{}", text.on_blue());
+    return text.on_blue().to_string();
 }
 
-fn get_is_selected(col_index: usize) -> bool {
-    unsafe {
-        return col_index == SELECTED_COLUMN;
+/// Formats text with the cell color, marking matches of the highlight patterns
+fn format_highlighted_text(
+    text: &str,
+    is_selected: bool,
+    is_selected_row: bool,
+    highlights: &[&Regex]
+) -> String {
+    let mut matches: Vec<(usize, usize)> = highlights
+        .iter()
+        .flat_map(|re| re.find_iter(text).map(|m| (m.start(), m.end())))
+        .filter(|(start, end)| start < end)
+        .collect();
+    matches.sort();
+    let mut formatted = String::new();
+    let mut position = 0;
+    for (start, end) in matches {
+        if end <= position {
+            continue;
+        }
+        let start = start.max(position);
+        formatted.push_str(
+            &format_selected_color(&text[position..start], is_selected, is_selected_row)
+        );
+        formatted.push_str(&text[start..end].black().on_yellow().to_string());
+        position = end;
     }
+    formatted.push_str(&format_selected_color(&text[position..], is_selected, is_selected_row));
+    return formatted;
 }
 
-fn get_sorted_processes(sys: &mut System) -> Vec<(&Pid, &Process)> {
-    unsafe {
-        let mut vec: Vec<_> = sys
-            .processes()
-            .iter()
-            .filter(|p| {
-                if SEARCH_TEXT.chars().count() == 0 {
-                    return true;
-                }
-                let re = Regex::new(SEARCH_TEXT.as_str()).unwrap();
-                return match SELECTED_COLUMN {
-                    1 => re.is_match(p.1.name().to_str().unwrap()),
-                    2 => re.is_match(p.1.cpu_usage().to_string().as_str()),
-                    3 => re.is_match(p.1.memory().to_string().as_str()),
-                    4 => re.is_match(p.1.run_time().to_string().as_str()),
-                    _ => re.is_match(p.0.as_u32().to_string().as_str()),
-                };
-            })
-            .collect();
-        vec.sort_by(|a, b| {
-            let comp = match SELECTED_COLUMN {
-                1 => a.1.name().partial_cmp(b.1.name()).unwrap(),
-                2 => a.1.cpu_usage().partial_cmp(&b.1.cpu_usage()).unwrap(),
-                3 => a.1.memory().partial_cmp(&b.1.memory()).unwrap(),
-                4 => a.1.run_time().partial_cmp(&b.1.run_time()).unwrap(),
-                // TODO this does not sort for PID?
-                _ => a.0.as_u32().partial_cmp(&a.0.as_u32()).unwrap(),
-            };
-            if SORT_DIRECTION == "ASC" {
-                return comp.reverse();
-            }
-            return comp;
-        });
-        return vec;
+/// Keeps the row cursor within the process list and scrolls so that it stays visible.
+/// Returns the row cursor and scroll offset.
+fn clamp_selected_row(app: &mut App, row_count: usize, page_size: usize) -> (usize, usize) {
+    app.process_page_size = page_size;
+    app.selected_row = app.selected_row.min(row_count.saturating_sub(1));
+    if app.selected_row < app.scroll_offset {
+        app.scroll_offset = app.selected_row;
     }
+    if app.selected_row >= app.scroll_offset + page_size {
+        app.scroll_offset = app.selected_row + 1 - page_size;
+    }
+    app.scroll_offset = app.scroll_offset.min(row_count.saturating_sub(page_size));
+    return (app.selected_row, app.scroll_offset);
 }
 
+/// Prints shown row range on the right side of the bottom border
+fn print_row_range(row: u16, scroll_offset: usize, page_size: usize, row_count: usize) {
+    let first = if row_count == 0 { 0 } else { scroll_offset + 1 };
+    let last = (scroll_offset + page_size).min(row_count);
+    let text = format!(" rows {}–{} of {} ", first, last, row_count);
+    let width = get_terminal_dimensions().0;
+    let col = width.saturating_sub((text.chars().count() as u16) + 1);
+    queue!(stdout(), cursor::MoveTo(col, row)).unwrap();
+    print!("{}", text.black().on_white());
+}
+
+/// Pads the header of the sort column to the cell width, the sort arrow ends the cell
 fn format_selected_header_text(
     header: &str,
-    col_index: usize,
-    col_width: u16,
-    cols_length: usize
+    alignment: Alignment,
+    is_selected: bool,
+    layout: &CellLayout,
+    sort_direction: SortDirection
 ) -> String {
-    let is_selected = get_is_selected(col_index);
     if !is_selected {
-        return format!("{header}");
-    }
-    let col = col_width * (col_index as u16);
-    let mut text = format!("{header}");
-    let mut col_print_len = format!("{}{}", COLUMN_SEPARATOR, header).chars().count() as u16;
-    if col_print_len > col_width - 1 {
-        text = truncate_text(text, col_width - 5);
-        col_print_len = format!("{}{}", COLUMN_SEPARATOR, text).chars().count() as u16;
+        return header.to_string();
     }
-    let mut white_spaces = col_width - col_print_len;
-    let is_last = col_index == cols_length - 1;
-    let width = get_terminal_dimensions().0;
-
-    if is_last {
-        white_spaces = width - col - col_print_len - 1;
+    let arrow = if sort_direction == SortDirection::DESC { '↓' } else { '↑' };
+    let arrow_text = match alignment {
+        Alignment::RIGHT => format!(" {}", arrow),
+        Alignment::LEFT => arrow.to_string(),
+    };
+    // Keeps a space before the next separator like right aligned text
+    let width = get_text_width(layout, Alignment::RIGHT);
+    let text = truncate_text(
+        header.to_string(),
+        width.saturating_sub(arrow_text.chars().count()) as u16
+    );
+    let padding = " ".repeat(
+        width.saturating_sub(text.chars().count() + arrow_text.chars().count())
+    );
+    if alignment == Alignment::RIGHT {
+        return format!("{}{}{}", padding, text, arrow_text);
     }
+    return format!("{}{}{}", text, padding, arrow_text);
+}
 
-    for _ in 0..white_spaces - 3 {
-        text.push(' ');
-    }
-    unsafe {
-        if SORT_DIRECTION == "ASC" {
-            text.push('↓');
-        } else {
-            text.push('↑');
-        }
+/// Prints the headers with the sort arrow on the selected column and returns the number of
+/// columns that fit the terminal width with the width of each
+fn print_header_row(
+    row: u16,
+    headers: &[(&str, Alignment)],
+    selected_column: usize,
+    sort_direction: SortDirection
+) -> (usize, u16) {
+    let width = get_terminal_dimensions().0;
+    let (cols_length, col_width) = get_column_layout(headers.len(), width);
+    for (i, (header, alignment)) in headers.iter().take(cols_length).enumerate() {
+        let is_selected = i == selected_column;
+        let layout = CellLayout { col_index: i, col_width, cols_length, width };
+        let text = format_selected_header_text(
+            header,
+            *alignment,
+            is_selected,
+            &layout,
+            sort_direction
+        );
+        print_column(
+            row,
+            layout,
+            text.as_str(),
+            CellStyle {
+                alignment: *alignment,
+                is_selected,
+                is_selected_row: false,
+                highlights: &[],
+                color: None,
+            }
+        );
     }
-    return text;
+    return (cols_length, col_width);
 }
 
-pub fn print_processes(start_row: u16, sys: &mut System) {
+pub fn print_processes(start_row: u16, snapshot: &Snapshot, app: &mut App) {
     let (width, height) = get_terminal_dimensions();
     let empty_before: u16 = 1;
+    let columns: Vec<_> = app.visible_columns
+        .iter()
+        .map(|index| &PROCESS_COLUMNS[*index])
+        .collect();
 
     let top_border = start_row + empty_before;
     print_row_separator(top_border);
 
     let header_row = top_border + 1;
-    queue!(stdout(), cursor::MoveTo(0, header_row)).unwrap();
-    let col_width = ((width as f32) / (PROCESS_HEADERS_LEN as f32)).floor() as u16;
-
     // TODO this should not be rendered on every cycle, move to only be printed on first cycle
-    for i in 0..PROCESS_HEADERS_LEN {
-        let header = PROCESS_HEADERS[i];
-        let formatted_text = format_selected_header_text(header, i, col_width, PROCESS_HEADERS_LEN);
-        print_column(header_row, i, col_width, PROCESS_HEADERS_LEN, formatted_text.as_str());
-    }
+    let headers: Vec<_> = columns
+        .iter()
+        .map(|column| (column.header, column.alignment))
+        .collect();
+    let (cols_length, col_width) = print_header_row(
+        header_row,
+        &headers,
+        app.selected_column,
+        app.sort_direction
+    );
 
     print_row_separator(header_row + 1);
 
-    let max_print_count = height - header_row - 4;
-    let processes = get_sorted_processes(sys);
+    let max_print_count = height.saturating_sub(header_row + 4);
+    let processes = get_process_rows(snapshot, app);
+    let (selected_row, scroll_offset) = clamp_selected_row(
+        app,
+        processes.len(),
+        max_print_count as usize
+    );
+    app.selected_process = processes
+        .get(selected_row)
+        .map(|(process, _)| SelectedProcess {
+            pid: process.pid,
+            name: process.name.to_string(),
+            start_time: process.start_time,
+        });
+    let highlights: Vec<Vec<&Regex>> = app.visible_columns
+        .iter()
+        .map(|index| {
+            return match &app.search_filter {
+                Some(filter) => filter.get_highlights(*index, app.selected_column_index()),
+                None => Vec::new(),
+            };
+        })
+        .collect();
     for i in 0..max_print_count {
-        if i >= max_print_count {
-            break;
+        let row = header_row + 2 + i;
+        let index = scroll_offset + (i as usize);
+        let process = processes.get(index);
+        let is_selected_row = index == selected_row && process.is_some();
+        for (col_index, column) in columns.iter().take(cols_length).enumerate() {
+            let value = match process {
+                Some((process, prefix)) if column.id == "name" => {
+                    format!("{}{}", prefix, (column.value)(process, app.unit_mode))
+                }
+                Some((process, _)) => {
+                    // Leaves room for the separator and a space on both sides
+                    column.format(
+                        process,
+                        &app.history,
+                        (col_width as usize).saturating_sub(3),
+                        app.unit_mode
+                    )
+                }
+                None => String::new(),
+            };
+            print_column(
+                row,
+                CellLayout { col_index, col_width, cols_length, width },
+                value.as_str(),
+                CellStyle {
+                    alignment: column.alignment,
+                    is_selected: col_index == app.selected_column,
+                    is_selected_row,
+                    highlights: &highlights[col_index],
+                    color: None,
+                }
+            );
+        }
+    }
+
+    print_row_separator(height.saturating_sub(2));
+    print_row_range(
+        height.saturating_sub(2),
+        scroll_offset,
+        max_print_count as usize,
+        processes.len()
+    );
+}
+
+/// Width of each column when columns share the terminal width evenly
+pub(super) fn get_column_width(column_count: usize) -> u16 {
+    return get_column_layout(column_count, get_terminal_dimensions().0).1;
+}
+
+pub(super) struct TableRow {
+    pub cells: Vec<String>,
+    /// Text color of all cells
+    pub color: Option<Color>,
+}
+
+/// Prints a table of headers and cells like the process table, without a row cursor.
+/// The selected column is marked as the sort column.
+pub(super) fn print_sorted_table(
+    start_row: u16,
+    headers: &[(&str, Alignment)],
+    selected_column: usize,
+    sort_direction: SortDirection,
+    rows: &[TableRow]
+) {
+    let (width, height) = get_terminal_dimensions();
+    let top_border = start_row + 1;
+    print_row_separator(top_border);
+
+    let header_row = top_border + 1;
+    let (cols_length, col_width) = print_header_row(
+        header_row,
+        headers,
+        selected_column,
+        sort_direction
+    );
+    print_row_separator(header_row + 1);
+
+    for i in 0..height.saturating_sub(header_row + 4) {
+        let table_row = rows.get(i as usize);
+        for (col_index, (_, alignment)) in headers.iter().take(cols_length).enumerate() {
+            let value = table_row
+                .and_then(|table_row| table_row.cells.get(col_index))
+                .map_or("", |value| value.as_str());
+            print_column(
+                header_row + 2 + i,
+                CellLayout { col_index, col_width, cols_length, width },
+                value,
+                CellStyle {
+                    alignment: *alignment,
+                    is_selected: col_index == selected_column,
+                    is_selected_row: false,
+                    highlights: &[],
+                    color: table_row.and_then(|table_row| table_row.color),
+                }
+            );
         }
+    }
+    print_row_separator(height.saturating_sub(2));
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// Characters printed for a cell from its separator up to the next separator
+    fn get_printed_width(text: &str, layout: &CellLayout, alignment: Alignment) -> usize {
+        let (text, white_spaces) = fit_cell_text(text, layout, alignment);
+        let trailing_space = if alignment == Alignment::RIGHT { 1 } else { 0 };
+        return COLUMN_SEPARATOR.chars().count() +
+            1 +
+            text.chars().count() +
+            (white_spaces as usize) +
+            trailing_space;
+    }
 
-        let mut process_id = String::new();
-        let mut process_name = String::new();
-        let mut cpu_usage = String::new();
-        let mut memory = String::new();
-        let mut run_time = String::new();
-
-        if i < (processes.len() as u16) {
-            let (pid, process) = processes[i as usize];
-            process_id = format!("{}", pid.as_u32());
-            process_name = format!("{:?}", process.name());
-            cpu_usage = format!("{}", process.cpu_usage());
-            memory = format!("{}", (process.memory() as f32) / 1000.0);
-            run_time = format!("{}", process.run_time());
+    /// Checks that every header, selected in both directions, and every cell fits its column
+    pub(in crate::run_mode::print) fn assert_table_fits(
+        headers: &[(&str, Alignment)],
+        rows: &[Vec<String>],
+        width: u16
+    ) {
+        let (cols_length, col_width) = get_column_layout(headers.len(), width);
+        assert!(cols_length >= 1 && cols_length <= headers.len());
+        assert!((cols_length as u16) * col_width <= width.max(col_width));
+        for (col_index, (header, alignment)) in headers.iter().take(cols_length).enumerate() {
+            let layout = CellLayout { col_index, col_width, cols_length, width };
+            let cell_width = layout.cell_width() as usize;
+            let mut texts: Vec<String> = rows
+                .iter()
+                .map(|row| row[col_index].to_string())
+                .collect();
+            for sort_direction in [SortDirection::ASC, SortDirection::DESC] {
+                for is_selected in [false, true] {
+                    texts.push(
+                        format_selected_header_text(
+                            header,
+                            *alignment,
+                            is_selected,
+                            &layout,
+                            sort_direction
+                        )
+                    );
+                }
+            }
+            for text in texts {
+                let printed_width = get_printed_width(&text, &layout, *alignment);
+                if width >= MIN_COLUMN_WIDTH {
+                    assert!(
+                        printed_width <= cell_width,
+                        "'{text}' takes {printed_width} of {cell_width} characters at width {width}"
+                    );
+                }
+            }
         }
+    }
 
-        let row = header_row + 2 + i;
-        print_column(row, 0, col_width, PROCESS_HEADERS_LEN, process_id.as_str());
-        print_column(row, 1, col_width, PROCESS_HEADERS_LEN, process_name.as_str());
-        print_column(row, 2, col_width, PROCESS_HEADERS_LEN, cpu_usage.as_str());
-        print_column(row, 3, col_width, PROCESS_HEADERS_LEN, memory.as_str());
-        print_column(row, 4, col_width, PROCESS_HEADERS_LEN, run_time.as_str());
+    #[test]
+    fn columns_keep_minimum_width() {
+        assert_eq!(get_column_layout(5, 100), (5, 20));
+        assert_eq!(get_column_layout(11, 60), (10, 6));
+        assert_eq!(get_column_layout(3, 4), (1, 4));
+        assert_eq!(get_column_layout(3, 0), (1, 0));
+    }
+
+    #[test]
+    fn narrow_process_table_fits() {
+        let headers: Vec<_> = PROCESS_COLUMNS.iter()
+            .map(|column| (column.header, column.alignment))
+            .collect();
+        let row: Vec<String> = headers
+            .iter()
+            .map(|(header, _)| format!("{} with a long value", header))
+            .collect();
+        for width in 0..=160 {
+            for count in [1, 2, 5, 11, headers.len()] {
+                let rows = [row[..count].to_vec(), vec![String::new(); count]];
+                assert_table_fits(&headers[..count], &rows, width);
+            }
+        }
     }
 
-    print_row_separator(height - 2);
+    #[test]
+    fn selected_header_ends_with_sort_arrow() {
+        let ascending = |header: &str, alignment: Alignment, layout: &CellLayout| {
+            return format_selected_header_text(header, alignment, true, layout, SortDirection::ASC);
+        };
+        let layout = CellLayout { col_index: 0, col_width: 20, cols_length: 2, width: 40 };
+        let left = ascending("Name", Alignment::LEFT, &layout);
+        assert!(left.starts_with("Name") && left.ends_with('↑'));
+        let right = format_selected_header_text(
+            "CPU",
+            Alignment::RIGHT,
+            true,
+            &layout,
+            SortDirection::DESC
+        );
+        assert!(right.ends_with("CPU ↓"));
+        let narrow = CellLayout {
+            col_index: 1,
+            col_width: MIN_COLUMN_WIDTH,
+            cols_length: 3,
+            width: 18,
+        };
+        assert_eq!(ascending("Memory", Alignment::LEFT, &narrow), "Me↑");
+        assert_eq!(ascending("PID", Alignment::RIGHT, &narrow), "P ↑");
+    }
 }
@@ -1,69 +1,392 @@
-use std::{ io::stdout, panic };
-use colored::Colorize;
+use std::{ io::stdout, time::Duration };
+use colored::{ ColoredString, Colorize };
 use crossterm::{ cursor, queue };
-use machine_info::{ GraphicsUsage, Machine };
-use sysinfo::System;
 
-use crate::utils::get_terminal_dimensions;
+use crate::{
+    format::{ format_bytes, format_elapsed, format_percent, format_rate, UnitMode },
+    history::History,
+    recording::Replay,
+    sensor_columns::get_sensor_color,
+    snapshot::{ CpuSnapshot, GpuSnapshot, HostSnapshot, Snapshot, StateCounts },
+    state::App,
+    utils::{
+        empty_row,
+        format_duration,
+        format_timestamp,
+        get_terminal_dimensions,
+        truncate_text,
+    },
+};
+
+/// Prints the sampling interval, marked as paused while new snapshots are not shown
+pub fn print_refresh_status(row: u16, interval: Duration, paused: bool) {
+    empty_row(row);
+    queue!(stdout(), cursor::MoveTo(0, row)).unwrap();
+    print!("Refresh interval: {}", format_duration(interval));
+    if paused {
+        print!(" {}", " PAUSED ".black().on_yellow());
+    }
+}
+
+/// Prints the position and time of the replayed snapshot, marked as paused while not playing
+pub fn print_replay_status(row: u16, replay: &Replay) {
+    empty_row(row);
+    queue!(stdout(), cursor::MoveTo(0, row)).unwrap();
+    print!(
+        "Replay {}/{}  {} UTC  Speed: {}x",
+        replay.position() + 1,
+        replay.len(),
+        format_timestamp(replay.current().timestamp_ms / 1000),
+        replay.speed
+    );
+    if !replay.playing {
+        print!(" {}", " PAUSED ".black().on_yellow());
+    }
+}
 
 /// Prints system resource usage to header and returns index of next empty row
-pub fn print_resource_header(sys: &mut System, start_row: u16) -> u16 {
-    print_memory_usage(start_row, sys);
-    print_cpu_usage(start_row + 1, sys);
-
-    let graphs_len = panic::catch_unwind(|| {
-        let m = Machine::new();
-        let graphics = m.graphics_status();
-        print_gpu_usage(start_row + 2, &graphics);
-        return graphics.len();
+pub fn print_resource_header(snapshot: &Snapshot, app: &App, start_row: u16) -> u16 {
+    print_memory_usage(start_row, snapshot, app.unit_mode);
+    print_swap_usage(start_row + 1, snapshot, app.unit_mode);
+    print_cpu_usage(start_row + 2, snapshot);
+    let mut next_row = start_row + 3;
+    if app.show_cores {
+        next_row += print_core_grid(next_row, &snapshot.cpus);
+    }
+    if let Some(gpus) = &snapshot.gpus {
+        print_gpu_usage(next_row, gpus);
+        next_row += gpus.len() as u16;
+    }
+    if app.show_network {
+        print_network_usage(next_row, snapshot, &app.history, app.unit_mode);
+        next_row += 1;
+    }
+    if app.show_hottest_sensor {
+        print_hottest_sensor(next_row, snapshot);
+        next_row += 1;
+    }
+    print_host_info(next_row, &snapshot.host);
+    print_task_counts(next_row + 2, snapshot);
+    return next_row + 3;
+}
+
+/// Prints text on the row, cut off at the terminal width
+fn print_info_row(row: u16, text: &str) {
+    let width = get_terminal_dimensions().0 as usize;
+    empty_row(row);
+    queue!(stdout(), cursor::MoveTo(0, row)).unwrap();
+    print!("{}", text.chars().take(width).collect::<String>());
+}
+
+/// Prints load averages, uptime and boot time on the row and host, kernel and OS on the next row
+fn print_host_info(row: u16, host: &HostSnapshot) {
+    let [one, five, fifteen] = host.load_average;
+    // Recordings made before host values were captured have no boot time
+    let (uptime, boot_time) = match host.boot_time {
+        0 => (String::from("-"), String::from("-")),
+        boot_time => (format_elapsed(host.uptime), format!("{} UTC", format_timestamp(boot_time))),
+    };
+    print_info_row(
+        row,
+        format!(
+            "Load average: {:.2} {:.2} {:.2}   Uptime: {}   Boot time: {}",
+            one,
+            five,
+            fifteen,
+            uptime,
+            boot_time
+        ).as_str()
+    );
+    let or_unknown = |text: &str| -> String {
+        return if text.is_empty() { String::from("-") } else { text.to_string() };
+    };
+    print_info_row(
+        row + 1,
+        format!(
+            "Host: {}   Kernel: {}   OS: {}",
+            or_unknown(&host.host_name),
+            or_unknown(&host.kernel_version),
+            or_unknown(&host.os_version)
+        ).as_str()
+    );
+}
+
+/// Prints throughput and totals summed over all interfaces except loopback
+fn print_network_usage(row: u16, snapshot: &Snapshot, history: &History, units: UnitMode) {
+    let networks: Vec<_> = snapshot.networks
+        .iter()
+        .filter(|network| network.name != "lo")
+        .collect();
+    let rates: Vec<_> = networks
+        .iter()
+        .filter_map(|network| history.get_network(&network.name).and_then(|history| history.rates))
+        .collect();
+    let format_total_rate = |rate: f64| -> String {
+        return if rates.is_empty() { String::from("-") } else { format_rate(rate, units) };
+    };
+    print_info_row(
+        row,
+        format!(
+            "Network: RX {}  TX {}   Total: RX {}  TX {}",
+            format_total_rate(rates.iter().map(|rates| rates.received).sum()),
+            format_total_rate(rates.iter().map(|rates| rates.transmitted).sum()),
+            format_bytes(networks.iter().map(|network| network.total_received).sum(), units),
+            format_bytes(networks.iter().map(|network| network.total_transmitted).sum(), units)
+        ).as_str()
+    );
+}
+
+fn print_hottest_sensor(row: u16, snapshot: &Snapshot) {
+    let hottest = snapshot.sensors
+        .iter()
+        .max_by(|a, b| a.temperature.total_cmp(&b.temperature));
+    let Some(sensor) = hottest else {
+        print_info_row(row, "Hottest sensor: -");
+        return;
+    };
+    let critical = sensor.critical.map_or(String::new(), |critical| {
+        return format!(" (critical {:.1}°C)", critical);
     });
+    let temperature = format!("{:.1}°C", sensor.temperature);
+    empty_row(row);
+    queue!(stdout(), cursor::MoveTo(0, row)).unwrap();
+    print!("Hottest sensor: {} ", sensor.label);
+    match get_sensor_color(sensor) {
+        Some(color) => print!("{}", temperature.color(color)),
+        None => print!("{}", temperature),
+    }
+    print!("{}", critical);
+}
+
+fn format_state_counts(counts: &StateCounts) -> String {
+    return format!(
+        "{} ({} running, {} sleeping, {} stopped, {} zombie)",
+        counts.total,
+        counts.running,
+        counts.sleeping,
+        counts.stopped,
+        counts.zombie
+    );
+}
 
-    if graphs_len.is_err() {
-        clearscreen::clear().expect("failed to clear");
-        return start_row + 2;
+fn print_task_counts(row: u16, snapshot: &Snapshot) {
+    print_info_row(
+        row,
+        format!(
+            "Processes: {}   Threads: {}",
+            format_state_counts(&snapshot.process_counts),
+            format_state_counts(&snapshot.thread_counts)
+        ).as_str()
+    );
+}
+
+fn print_cpu_usage(row: u16, snapshot: &Snapshot) {
+    let usage = snapshot.global_cpu_usage;
+    print_usage_bar(row, &format!("CPU usage: {}", format_percent(usage as f64)), usage / 100.0);
+}
+
+/// Detail of a core cell, the grid picks the most detailed one that fits
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Clone, Copy)]
+enum CoreCell {
+    /// Usage bar, percentage and frequency
+    FREQUENCY,
+    /// Usage bar and percentage
+    BAR,
+    /// Percentage only
+    USAGE,
+}
+
+const CORE_CELL_GAP: usize = 2;
+const MIN_CORE_BAR_WIDTH: usize = 5;
+
+impl CoreCell {
+    /// Width of the cell without the bar
+    fn text_width(&self, label_width: usize) -> usize {
+        return match self {
+            // "12 [] 100.0% 3.20GHz"
+            CoreCell::FREQUENCY => label_width + 18,
+            // "12 [] 100.0%"
+            CoreCell::BAR => label_width + 10,
+            // "12 100%"
+            CoreCell::USAGE => label_width + 5,
+        };
+    }
+
+    fn min_width(&self, label_width: usize) -> usize {
+        return match self {
+            CoreCell::USAGE => self.text_width(label_width),
+            _ => self.text_width(label_width) + MIN_CORE_BAR_WIDTH,
+        };
     }
-    return start_row + 2 + (graphs_len.unwrap() as u16);
 }
 
-fn print_cpu_usage(row: u16, sys: &mut System) {
-    print_resource_usage(row, "CPU", sys.global_cpu_usage(), 100.0);
+fn color_by_usage(text: String, usage: f32) -> ColoredString {
+    return match usage {
+        usage if usage >= 90.0 => text.red(),
+        usage if usage >= 60.0 => text.yellow(),
+        _ => text.green(),
+    };
 }
 
-fn print_gpu_temp(row: u16, graphics_usage: &GraphicsUsage, gpu_index: usize) {
-    queue!(stdout(), cursor::MoveTo(0, row)).unwrap();
-    print!("GPU {} temperature: {}°C", gpu_index, graphics_usage.temperature);
+fn format_frequency(mhz: u64) -> String {
+    if mhz >= 1000 {
+        return format!("{:.2}GHz", (mhz as f64) / 1000.0);
+    }
+    return format!("{}MHz", mhz);
 }
 
-/// NOTE: machine_info seems to be rather unstable, throwing sometimes on access
-fn print_gpu_usage(start_row: u16, graphics: &Vec<GraphicsUsage>) {
-    for i in 0..graphics.len() {
-        let graphics_usage = &graphics[i];
-        print_resource_usage(
-            start_row + (i as u16),
-            format!("GPU {}", i).as_str(),
-            graphics_usage.gpu as f32,
-            100.0
-        );
-        print_gpu_temp(start_row + (i as u16) + 1, graphics_usage, i);
+/// Formats a core padded to width, the bar takes the space left by the text
+fn format_core(index: usize, cpu: &CpuSnapshot, cell: CoreCell, label_width: usize, width: usize) -> String {
+    let label = format!("{:>label_width$}", index);
+    if cell == CoreCell::USAGE {
+        let usage = color_by_usage(format!("{:>3.0}%", cpu.usage), cpu.usage);
+        return format!("{} {}{}", label, usage, " ".repeat(width - cell.text_width(label_width)));
     }
+    let bar_width = width - cell.text_width(label_width);
+    let filled = (((cpu.usage.clamp(0.0, 100.0) / 100.0) * (bar_width as f32)).round() as usize).min(
+        bar_width
+    );
+    let bar = color_by_usage("|".repeat(filled), cpu.usage);
+    let mut text = format!(
+        "{} [{}{}] {:>6}",
+        label,
+        bar,
+        " ".repeat(bar_width - filled),
+        format_percent(cpu.usage as f64)
+    );
+    if cell == CoreCell::FREQUENCY {
+        text.push_str(format!(" {:>7}", format_frequency(cpu.frequency)).as_str());
+    }
+    return text;
 }
 
-fn print_memory_usage(row: u16, sys: &mut System) {
-    print_resource_usage(row, "Memory", sys.used_memory() as f32, sys.total_memory() as f32);
+/// Prints the cores in columns, numbered top to bottom and then left to right.
+/// Less detail is shown when the grid would otherwise take more than a quarter of the terminal.
+/// Returns the number of rows printed.
+fn print_core_grid(start_row: u16, cpus: &[CpuSnapshot]) -> u16 {
+    if cpus.is_empty() {
+        return 0;
+    }
+    let (width, height) = get_terminal_dimensions();
+    let width = width as usize;
+    let label_width = (cpus.len() - 1).to_string().len();
+    let max_rows = ((height / 4) as usize).max(1);
+    let get_layout = |cell: CoreCell| {
+        let columns = ((width + CORE_CELL_GAP) / (cell.min_width(label_width) + CORE_CELL_GAP))
+            .clamp(1, cpus.len());
+        let rows = cpus.len().div_ceil(columns);
+        // Filling columns top to bottom can leave the last columns empty
+        return (cell, cpus.len().div_ceil(rows), rows);
+    };
+    let (cell, columns, rows) = [CoreCell::FREQUENCY, CoreCell::BAR]
+        .into_iter()
+        .map(get_layout)
+        .find(|(_, _, rows)| *rows <= max_rows)
+        .unwrap_or_else(|| get_layout(CoreCell::USAGE));
+    let cell_width = ((width + CORE_CELL_GAP) / columns)
+        .saturating_sub(CORE_CELL_GAP)
+        .max(cell.min_width(label_width));
+
+    for row in 0..rows {
+        empty_row(start_row + (row as u16));
+        queue!(stdout(), cursor::MoveTo(0, start_row + (row as u16))).unwrap();
+        let cells: Vec<String> = (0..columns)
+            .map(|column| column * rows + row)
+            .filter(|index| *index < cpus.len())
+            .map(|index| format_core(index, &cpus[index], cell, label_width, cell_width))
+            .collect();
+        print!("{}", cells.join(" ".repeat(CORE_CELL_GAP).as_str()));
+    }
+    return rows as u16;
 }
 
-fn print_resource_usage(row: u16, resource: &str, used: f32, total: f32) {
-    let resource_usage = used / total;
+fn get_gpu_label(index: usize, gpu: &GpuSnapshot) -> String {
+    if gpu.name.is_empty() {
+        return format!("GPU {}", index);
+    }
+    return format!("GPU {} ({})", index, gpu.name);
+}
+
+/// Prints one row per card, values the card does not report are shown as unavailable
+fn print_gpu_usage(start_row: u16, gpus: &[GpuSnapshot]) {
+    for (i, gpu) in gpus.iter().enumerate() {
+        let row = start_row + (i as u16);
+        let label = get_gpu_label(i, gpu);
+        let temperature = gpu.temperature.map(|temperature| format!("{}°C", temperature));
+        match (gpu.usage, temperature) {
+            (Some(usage), temperature) => {
+                let mut info_text = format!("{} usage: {}", label, format_percent(usage as f64));
+                if let Some(temperature) = temperature {
+                    info_text.push_str(format!(" {}", temperature).as_str());
+                }
+                print_usage_bar(row, &info_text, (usage as f32) / 100.0);
+            }
+            (None, Some(temperature)) => {
+                print_info_row(row, format!("{} usage: unavailable {}", label, temperature).as_str());
+            }
+            (None, None) => print_info_row(row, format!("{}: unavailable", label).as_str()),
+        }
+    }
+}
+
+fn print_memory_usage(row: u16, snapshot: &Snapshot, units: UnitMode) {
+    print_resource_usage(row, "Memory", snapshot.used_memory, snapshot.total_memory, units);
+}
+
+fn print_swap_usage(row: u16, snapshot: &Snapshot, units: UnitMode) {
+    if snapshot.total_swap == 0 {
+        print_info_row(row, "Swap: not configured");
+        return;
+    }
+    print_resource_usage(row, "Swap", snapshot.used_swap, snapshot.total_swap, units);
+}
+
+/// Prints used and total bytes with the usage bar, e.g. "Memory: 1.2 GiB / 15.6 GiB (7.7%)"
+fn print_resource_usage(row: u16, resource: &str, used: u64, total: u64, units: UnitMode) {
+    let resource_usage = if total == 0 { 0.0 } else { (used as f32) / (total as f32) };
+    let info_text = format!(
+        "{}: {} / {} ({})",
+        resource,
+        format_bytes(used, units),
+        format_bytes(total, units),
+        format_percent((resource_usage * 100.0) as f64)
+    );
+    print_usage_bar(row, &info_text, resource_usage);
+}
+
+/// Bars narrower than this are left out, the text is then printed alone
+const MIN_USAGE_BAR_WIDTH: u16 = 5;
+
+/// Width of the bar after the text, at most 70% of the row and leaving a space after the text.
+/// 0 when there is no room for a bar.
+fn get_usage_bar_width(width: u16, text_length: u16) -> u16 {
+    let usage_bar_width = (((width as f32) * 0.7).floor() as u16).min(
+        width.saturating_sub(text_length.saturating_add(1))
+    );
+    if usage_bar_width < MIN_USAGE_BAR_WIDTH {
+        return 0;
+    }
+    return usage_bar_width;
+}
+
+/// Prints the text followed by a bar over the right part of the row, filled by the usage fraction
+fn print_usage_bar(row: u16, info_text: &str, resource_usage: f32) {
     let width = get_terminal_dimensions().0;
-    let info_text = format!("{} usage: {}%", resource, resource_usage * 100.0);
-    let usage_bar_width = ((width as f32) * 0.7).floor() as u16;
-    let white_space_count = width - usage_bar_width - (info_text.chars().count() as u16);
+    let info_text = truncate_text(info_text.to_string(), width);
+    let text_length = info_text.chars().count() as u16;
+    let usage_bar_width = get_usage_bar_width(width, text_length);
 
     queue!(stdout(), cursor::MoveTo(0, row)).unwrap();
 
     print!("{}", info_text);
+    if usage_bar_width == 0 {
+        // Clears a bar printed while the row was wider
+        print!("{}", " ".repeat(width.saturating_sub(text_length) as usize));
+        return;
+    }
 
-    for _ in 0..white_space_count {
+    for _ in 0..width - usage_bar_width - text_length {
         print!(" ");
     }
 
@@ -77,3 +400,28 @@ fn print_resource_usage(row: u16, resource: &str, used: f32, total: f32) {
     }
     print!("]");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_bar_fits_after_text() {
+        for width in 0..=200 {
+            for text_length in [0, 10, 38, 60, 120] {
+                let usage_bar_width = get_usage_bar_width(width, text_length);
+                if usage_bar_width > 0 {
+                    assert!(usage_bar_width >= MIN_USAGE_BAR_WIDTH);
+                    assert!(text_length + 1 + usage_bar_width <= width, "width {width}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn usage_bar_takes_at_most_70_percent() {
+        assert_eq!(get_usage_bar_width(200, 20), 140);
+        assert_eq!(get_usage_bar_width(80, 38), 41);
+        assert_eq!(get_usage_bar_width(40, 38), 0);
+    }
+}
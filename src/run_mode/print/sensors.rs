@@ -0,0 +1,38 @@
+use super::process_table::{ print_sorted_table, TableRow };
+use crate::{
+    sensor_columns::{ get_sensor_color, SENSOR_COLUMNS },
+    snapshot::{ SensorSnapshot, Snapshot },
+    state::App,
+    utils::print_on_position,
+};
+
+/// Sensors of the snapshot sorted by the sensor sort column
+fn get_sensor_rows<'a>(snapshot: &'a Snapshot, app: &App) -> Vec<&'a SensorSnapshot> {
+    let mut rows: Vec<&SensorSnapshot> = snapshot.sensors.iter().collect();
+    let column = &SENSOR_COLUMNS[app.sensor_sort_column];
+    rows.sort_by(|a, b| app.sensor_sort_direction.apply(column.compare(a, b)));
+    return rows;
+}
+
+/// Prints the temperature sensors as a table sorted like the process table,
+/// rows are colored by how close the sensor is to its critical temperature
+pub fn print_sensors(start_row: u16, snapshot: &Snapshot, app: &App) {
+    let headers: Vec<_> = SENSOR_COLUMNS.iter()
+        .map(|column| (column.header, column.alignment))
+        .collect();
+    let rows: Vec<TableRow> = get_sensor_rows(snapshot, app)
+        .iter()
+        .map(|sensor| {
+            return TableRow {
+                cells: SENSOR_COLUMNS.iter()
+                    .map(|column| (column.value)(sensor))
+                    .collect(),
+                color: get_sensor_color(sensor),
+            };
+        })
+        .collect();
+    print_sorted_table(start_row, &headers, app.sensor_sort_column, app.sensor_sort_direction, &rows);
+    if snapshot.sensors.is_empty() {
+        print_on_position(" No temperature sensors found", start_row + 4, 1);
+    }
+}
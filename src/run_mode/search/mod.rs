@@ -1,76 +1,170 @@
-use std::io::stdout;
+use std::io::{ stdout, Write };
 
-use crossterm::{ cursor, event::{ read, Event, KeyCode, KeyEvent, KeyEventKind }, queue };
+use crossterm::{ cursor, event::{ Event, KeyCode, KeyEvent, KeyEventKind }, queue };
 
 use crate::{
-    state::{ Mode, MODE, SEARCH_TEXT },
+    filter::parse_filter,
+    state::{ App, Mode, SearchSession },
     utils::{
         delete_previous_character,
         empty_row,
         enter_input_mode,
         get_terminal_dimensions,
+        move_cursor,
         navigate_left_input,
         navigate_right_input,
+        print_at_end_of_row,
+        print_error_at_end_of_row,
         print_input,
+        print_on_last_row,
     },
 };
 
-pub fn run_search_mode() {
+const INPUT_PREFIX: &str = "Search: ";
+
+/// Starts editing the search text on the last row
+pub fn start_search_session(app: &mut App) {
+    let cursor_start_position = enter_input_mode(INPUT_PREFIX);
+    app.search_session = Some(SearchSession {
+        input: app.search_text.to_string(),
+        cursor_position: cursor_start_position + (app.search_text.chars().count() as u16),
+        cursor_start_position,
+        case_insensitive: app.search_case_insensitive,
+        literal: app.search_literal,
+        error: None,
+        previous_filter: app.search_filter.clone(),
+    });
+    app.mode = Mode::SEARCH;
+    print_search_row(app);
+}
+
+fn end_search_session(app: &mut App) {
+    app.search_session = None;
+    app.mode = Mode::PRINT;
+    empty_row(get_terminal_dimensions().1);
+    queue!(stdout(), cursor::Hide).unwrap();
+    stdout().flush().expect("failed to flush");
+}
+
+/// Reprints the search row with active toggles or the input error, does nothing outside a search
+pub fn print_search_row(app: &App) {
+    let Some(session) = &app.search_session else {
+        return;
+    };
     let height = get_terminal_dimensions().1;
     empty_row(height);
-    unsafe {
-        let input_prefix = "Search: ";
-        let cursor_start_position = enter_input_mode(&input_prefix);
-        let mut input = format!("{}", SEARCH_TEXT);
-        let mut cursor_position: u16 = cursor_start_position + (input.chars().count() as u16);
-        while MODE == Mode::SEARCH {
-            match read().unwrap() {
-                Event::Key(KeyEvent { code: KeyCode::Esc, kind: KeyEventKind::Press, .. }) => {
-                    MODE = Mode::PRINT;
-                }
-                Event::Key(KeyEvent { code: KeyCode::Enter, kind: KeyEventKind::Press, .. }) => {
-                    SEARCH_TEXT = format!("{}", input);
-                    MODE = Mode::PRINT;
-                }
-                Event::Key(
-                    KeyEvent { code: KeyCode::Backspace, kind: KeyEventKind::Press, .. },
-                ) => {
-                    cursor_position = delete_previous_character(
-                        input_prefix,
-                        &mut input,
-                        height,
-                        cursor_position,
-                        cursor_start_position
-                    );
-                }
-                Event::Key(KeyEvent { code: KeyCode::Left, kind: KeyEventKind::Press, .. }) => {
-                    cursor_position = navigate_left_input(
-                        height,
-                        cursor_position,
-                        cursor_start_position
-                    );
-                }
-                Event::Key(KeyEvent { code: KeyCode::Right, kind: KeyEventKind::Press, .. }) => {
-                    cursor_position = navigate_right_input(
-                        &input,
-                        height,
-                        cursor_position,
-                        cursor_start_position
-                    );
-                }
-                Event::Key(KeyEvent { code, kind: KeyEventKind::Press, .. }) => {
-                    cursor_position = print_input(
-                        &mut input,
-                        code,
-                        height,
-                        cursor_position,
-                        cursor_start_position
-                    );
-                }
-                _ => (),
+    print_on_last_row(format!("{INPUT_PREFIX}{}", session.input).as_str());
+    match &session.error {
+        Some(error) => print_error_at_end_of_row(format!("Error: {error}").as_str(), height),
+        None => {
+            let options = format!(
+                "[{}] F2 - ignore case [{}] F3 - literal",
+                if session.case_insensitive { "x" } else { " " },
+                if session.literal { "x" } else { " " }
+            );
+            print_at_end_of_row(options.as_str(), height);
+        }
+    }
+    move_cursor(height, session.cursor_position);
+    queue!(stdout(), cursor::Show).unwrap();
+    stdout().flush().expect("failed to flush");
+}
+
+/// Makes the input the active filter if it parses, otherwise keeps the error for display
+fn apply_search_input(app: &mut App, session: &mut SearchSession) {
+    match parse_filter(&session.input, session.case_insensitive, session.literal) {
+        Ok(filter) => {
+            app.search_filter = filter;
+            session.error = None;
+        }
+        Err(error) => {
+            session.error = Some(error);
+        }
+    }
+}
+
+/// Handles a key press while searching, returns true if the process table needs to be reprinted
+pub fn handle_search_input(app: &mut App, input: Event) -> bool {
+    let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, .. }) = input else {
+        return false;
+    };
+    // Taken out of the app while editing, so the filter can be updated alongside
+    let Some(mut session) = app.search_session.take() else {
+        return false;
+    };
+    let height = get_terminal_dimensions().1;
+    let mut filter_changed = true;
+    match code {
+        KeyCode::Esc => {
+            app.search_filter = session.previous_filter.take();
+            end_search_session(app);
+            return true;
+        }
+        KeyCode::Enter => {
+            apply_search_input(app, &mut session);
+            if session.error.is_none() {
+                app.search_text = session.input.to_string();
+                app.search_case_insensitive = session.case_insensitive;
+                app.search_literal = session.literal;
+                end_search_session(app);
+                return true;
             }
+            filter_changed = false;
+        }
+        KeyCode::F(2) => {
+            session.case_insensitive = !session.case_insensitive;
+            apply_search_input(app, &mut session);
+        }
+        KeyCode::F(3) => {
+            session.literal = !session.literal;
+            apply_search_input(app, &mut session);
+        }
+        KeyCode::Backspace => {
+            session.cursor_position = delete_previous_character(
+                INPUT_PREFIX,
+                &mut session.input,
+                height,
+                session.cursor_position,
+                session.cursor_start_position
+            );
+            apply_search_input(app, &mut session);
+        }
+        KeyCode::Left => {
+            session.cursor_position = navigate_left_input(
+                height,
+                session.cursor_position,
+                session.cursor_start_position
+            );
+            app.search_session = Some(session);
+            return false;
+        }
+        KeyCode::Right => {
+            session.cursor_position = navigate_right_input(
+                &session.input,
+                height,
+                session.cursor_position,
+                session.cursor_start_position
+            );
+            app.search_session = Some(session);
+            return false;
+        }
+        code => {
+            session.cursor_position = print_input(
+                &mut session.input,
+                code,
+                height,
+                session.cursor_position,
+                session.cursor_start_position
+            );
+            apply_search_input(app, &mut session);
         }
     }
-    empty_row(height);
-    queue!(stdout(), cursor::Hide).unwrap();
+    app.search_session = Some(session);
+    print_search_row(app);
+    return filter_changed;
+}
+
+pub fn run_search_mode(app: &mut App) {
+    start_search_session(app);
+    super::print::run_print_mode(app);
 }
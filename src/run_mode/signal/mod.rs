@@ -0,0 +1,107 @@
+use std::io::stdout;
+
+use crossterm::{ cursor, event::{ read, Event, KeyCode, KeyEvent, KeyEventKind }, queue };
+use sysinfo::Pid;
+
+use super::handle_control_key;
+use crate::{
+    process_signal::{
+        format_signal_result,
+        read_start_time,
+        send_signal,
+        ProcessSignal,
+        PROCESS_SIGNALS,
+    },
+    state::{ App, Mode, SelectedProcess },
+    utils::{
+        empty_row,
+        get_terminal_dimensions,
+        print_at_end_of_row,
+        print_error_at_end_of_row,
+        print_on_last_row,
+    },
+};
+
+/// Waits for a key press and returns its code, ignoring releases and other events.
+/// Returns None after Ctrl-C or Ctrl-Z, which cancel the prompt.
+fn read_key_press(app: &mut App) -> Option<KeyCode> {
+    loop {
+        let event = read().unwrap();
+        if handle_control_key(app, &event) {
+            return None;
+        }
+        if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, .. }) = event {
+            return Some(code);
+        }
+    }
+}
+
+/// Prompts the user to pick a signal on the last row, returns None if cancelled
+fn pick_signal(app: &mut App, height: u16) -> Option<ProcessSignal> {
+    let options: Vec<String> = PROCESS_SIGNALS.iter()
+        .map(|signal| format!("({}){}", signal.key(), signal.name()))
+        .collect();
+    empty_row(height);
+    print_on_last_row(format!("Signal: {} Esc - cancel", options.join(" ")).as_str());
+    loop {
+        match read_key_press(app) {
+            None | Some(KeyCode::Esc) => {
+                return None;
+            }
+            Some(KeyCode::Char(c)) => {
+                let signal = PROCESS_SIGNALS.into_iter().find(|signal| signal.key() == c);
+                if signal.is_some() {
+                    return signal;
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn confirm_signal(app: &mut App, height: u16, pid: Pid, name: &str, signal: ProcessSignal) -> bool {
+    empty_row(height);
+    print_on_last_row(
+        format!("Send SIG{} to {} ({})? [y/N]", signal.name(), pid.as_u32(), name).as_str()
+    );
+    return matches!(read_key_press(app), Some(KeyCode::Char('y') | KeyCode::Char('Y')));
+}
+
+pub fn run_signal_mode(app: &mut App) {
+    let height = get_terminal_dimensions().1;
+    let pending_signal = app.pending_signal.take();
+    app.mode = Mode::PRINT;
+    let Some(selected) = app.selected_process.clone() else {
+        empty_row(height);
+        print_error_at_end_of_row("Error: No process selected", height);
+        return;
+    };
+    let signal = match pending_signal {
+        Some(signal) => Some(signal),
+        None => pick_signal(app, height),
+    };
+    empty_row(height);
+    if let Some(signal) = signal {
+        let SelectedProcess { pid, name, start_time } = selected;
+        if !confirm_signal(app, height, pid, name.as_str(), signal) {
+            empty_row(height);
+        } else if read_start_time(pid) != Some(start_time) {
+            // The process exited and the PID may belong to another process by now
+            empty_row(height);
+            print_error_at_end_of_row(
+                format!("Error: {} ({}) has exited, no signal sent", pid.as_u32(), name).as_str(),
+                height
+            );
+        } else {
+            let result = send_signal(pid, signal);
+            empty_row(height);
+            let text = format_signal_result(pid, signal, &result);
+            if result.is_ok() {
+                print_at_end_of_row(text.as_str(), height);
+            } else {
+                print_error_at_end_of_row(text.as_str(), height);
+            }
+        }
+    }
+    queue!(stdout(), cursor::Hide).unwrap();
+}
@@ -0,0 +1,108 @@
+use std::{
+    path::PathBuf,
+    sync::{ mpsc::{ channel, sync_channel, Receiver, RecvTimeoutError, Sender }, Arc },
+    thread,
+    time::Duration,
+};
+
+use sysinfo::{
+    Components,
+    CpuRefreshKind,
+    Disks,
+    Networks,
+    RefreshKind,
+    System,
+    Users,
+    MINIMUM_CPU_UPDATE_INTERVAL,
+};
+
+use crate::{ gpu::GpuReader, snapshot::Snapshot };
+
+/// Refreshes the system on a background thread, publishing a snapshot after every refresh.
+/// The thread stops once the sampler is dropped.
+pub struct Sampler {
+    pub snapshots: Receiver<Arc<Snapshot>>,
+    /// Sending an interval restarts the wait, so the next snapshot is taken right away
+    intervals: Sender<Duration>,
+    interval: Duration,
+}
+
+impl Sampler {
+    /// GPUs are read from sysfs below sysfs_root
+    pub fn start(interval: Duration, sysfs_root: PathBuf) -> Sampler {
+        // Holds one snapshot, so the sampler waits while the UI is not reading instead of queueing
+        let (snapshot_sender, snapshots) = sync_channel(1);
+        let (intervals, interval_receiver) = channel();
+        thread::spawn(move || {
+            let mut sys = System::new_with_specifics(
+                RefreshKind::new().with_cpu(CpuRefreshKind::everything())
+            );
+            let mut users = Users::new_with_refreshed_list();
+            let mut disks = Disks::new();
+            let mut networks = Networks::new();
+            let mut components = Components::new();
+            let mut gpus = GpuReader::new(sysfs_root);
+            let mut interval = interval;
+            // CPU usage is computed from the difference between two refreshes
+            sys.refresh_all();
+            thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+            loop {
+                sys.refresh_all();
+                users.refresh_list();
+                // Picks up filesystems mounted since the last refresh
+                disks.refresh_list();
+                networks.refresh_list();
+                if components.list().is_empty() {
+                    // Listing again would reset the maximum temperatures, so it stops once sensors are found
+                    components.refresh_list();
+                } else {
+                    components.refresh();
+                }
+                let snapshot = Snapshot::capture(
+                    &sys,
+                    &users,
+                    &disks,
+                    &networks,
+                    &components,
+                    &mut gpus
+                );
+                if snapshot_sender.send(Arc::new(snapshot)).is_err() {
+                    return;
+                }
+                match interval_receiver.recv_timeout(interval) {
+                    Ok(new_interval) => {
+                        // Interval changes queued up while the UI was not reading only cause one snapshot
+                        interval = interval_receiver.try_iter().last().unwrap_or(new_interval);
+                    }
+                    Err(RecvTimeoutError::Timeout) => (),
+                    Err(RecvTimeoutError::Disconnected) => {
+                        return;
+                    }
+                }
+            }
+        });
+        return Sampler { snapshots, intervals, interval };
+    }
+
+    /// Sampler without a thread that never publishes snapshots, used while replaying a recording
+    pub fn idle(interval: Duration) -> Sampler {
+        let (_, snapshots) = sync_channel(1);
+        let (intervals, _) = channel();
+        return Sampler { snapshots, intervals, interval };
+    }
+
+    pub fn interval(&self) -> Duration {
+        return self.interval;
+    }
+
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+        // Fails only if the sampler thread has stopped, which is noticed when receiving snapshots
+        let _ = self.intervals.send(interval);
+    }
+
+    /// Takes a snapshot right away instead of waiting for the interval to pass
+    pub fn refresh(&self) {
+        let _ = self.intervals.send(self.interval);
+    }
+}
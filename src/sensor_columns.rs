@@ -0,0 +1,84 @@
+use std::cmp::Ordering;
+
+use colored::Color;
+
+use crate::{ process_columns::{ Alignment, SortKey }, snapshot::SensorSnapshot };
+
+pub struct SensorColumn {
+    /// Identifier used for picking the column from the command line
+    pub id: &'static str,
+    pub header: &'static str,
+    pub alignment: Alignment,
+    /// Text printed in the table cell
+    pub value: fn(&SensorSnapshot) -> String,
+    pub sort_key: fn(&SensorSnapshot) -> SortKey,
+}
+
+impl SensorColumn {
+    pub fn compare(&self, a: &SensorSnapshot, b: &SensorSnapshot) -> Ordering {
+        return (self.sort_key)(a).compare(&(self.sort_key)(b));
+    }
+}
+
+pub fn find_sensor_column(id: &str) -> Option<usize> {
+    return SENSOR_COLUMNS.iter().position(|column| column.id == id.to_lowercase());
+}
+
+/// Red from 90% of the critical temperature, yellow from 75%, None without a critical temperature
+pub fn get_sensor_color(sensor: &SensorSnapshot) -> Option<Color> {
+    return sensor.critical_fraction().map(|fraction| {
+        return match fraction {
+            fraction if fraction >= 0.9 => Color::Red,
+            fraction if fraction >= 0.75 => Color::Yellow,
+            _ => Color::Green,
+        };
+    });
+}
+
+fn format_temperature(temperature: f32) -> String {
+    return format!("{:.1}", temperature);
+}
+
+pub const SENSOR_COLUMNS: [SensorColumn; 5] = [
+    SensorColumn {
+        id: "label",
+        header: "Sensor",
+        alignment: Alignment::LEFT,
+        value: |sensor| sensor.label.to_string(),
+        sort_key: |sensor| SortKey::TEXT(sensor.label.to_string()),
+    },
+    SensorColumn {
+        id: "temp",
+        header: "Temperature (°C)",
+        alignment: Alignment::RIGHT,
+        value: |sensor| format_temperature(sensor.temperature),
+        sort_key: |sensor| SortKey::NUMBER(sensor.temperature as f64),
+    },
+    SensorColumn {
+        id: "max",
+        header: "Max (°C)",
+        alignment: Alignment::RIGHT,
+        value: |sensor| format_temperature(sensor.max),
+        sort_key: |sensor| SortKey::NUMBER(sensor.max as f64),
+    },
+    SensorColumn {
+        id: "critical",
+        header: "Critical (°C)",
+        alignment: Alignment::RIGHT,
+        value: |sensor| sensor.critical.map_or(String::from("-"), format_temperature),
+        sort_key: |sensor| SortKey::NUMBER(sensor.critical.map_or(-1.0, |critical| critical as f64)),
+    },
+    SensorColumn {
+        id: "critical_pct",
+        header: "Of critical (%)",
+        alignment: Alignment::RIGHT,
+        value: |sensor| {
+            sensor
+                .critical_fraction()
+                .map_or(String::from("-"), |fraction| format!("{:.0}", fraction * 100.0))
+        },
+        sort_key: |sensor| {
+            SortKey::NUMBER(sensor.critical_fraction().map_or(-1.0, |fraction| fraction as f64))
+        },
+    },
+];
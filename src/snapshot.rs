@@ -0,0 +1,341 @@
+use std::{ collections::HashMap, fs, time::{ SystemTime, UNIX_EPOCH } };
+
+use sysinfo::{
+    Components,
+    Disk,
+    Disks,
+    NetworkData,
+    Networks,
+    Pid,
+    Process,
+    ProcessStatus,
+    System,
+    ThreadKind,
+    Users,
+};
+
+use crate::{
+    gpu::GpuReader,
+    procfs::{ read_disk_stats, read_stat_field },
+    utils::strip_closing_quotes,
+};
+
+/// Process values copied out of sysinfo, so that snapshots can be handed between threads
+pub struct ProcessSnapshot {
+    pub pid: Pid,
+    pub name: String,
+    pub cpu_usage: f32,
+    /// Resident memory in bytes
+    pub memory: u64,
+    pub virtual_memory: u64,
+    /// Seconds since the process started
+    pub run_time: u64,
+    /// Seconds since epoch
+    pub start_time: u64,
+    pub user: String,
+    pub status: String,
+    pub parent: Option<Pid>,
+    pub threads: usize,
+    /// Bytes read since the previous refresh
+    pub read_bytes: u64,
+    /// Bytes written since the previous refresh
+    pub written_bytes: u64,
+    pub command: String,
+    pub executable: String,
+    pub nice: Option<i64>,
+}
+
+pub struct CpuSnapshot {
+    /// Usage in percent
+    pub usage: f32,
+    /// MHz
+    pub frequency: u64,
+}
+
+pub struct GpuSnapshot {
+    /// Vendor, empty when unknown
+    pub name: String,
+    /// Utilization in percent, None when the driver does not report it
+    pub usage: Option<u32>,
+    /// Degrees Celsius
+    pub temperature: Option<u32>,
+}
+
+pub struct DiskSnapshot {
+    /// Device, e.g. "/dev/sda1"
+    pub name: String,
+    pub mount_point: String,
+    pub file_system: String,
+    /// Bytes
+    pub total_space: u64,
+    pub available_space: u64,
+    /// Bytes read since boot, None when the device has no I/O statistics
+    pub total_read_bytes: Option<u64>,
+    pub total_written_bytes: Option<u64>,
+}
+
+/// Counters of a network interface since boot
+pub struct NetworkSnapshot {
+    pub name: String,
+    /// Bytes
+    pub total_received: u64,
+    pub total_transmitted: u64,
+    pub total_packets_received: u64,
+    pub total_packets_transmitted: u64,
+    pub total_errors_received: u64,
+    pub total_errors_transmitted: u64,
+}
+
+/// Temperature sensor, values in degrees Celsius
+pub struct SensorSnapshot {
+    pub label: String,
+    pub temperature: f32,
+    /// Highest temperature since the sampler started
+    pub max: f32,
+    pub critical: Option<f32>,
+}
+
+impl SensorSnapshot {
+    /// Temperature as a fraction of the critical temperature, None without a critical temperature
+    pub fn critical_fraction(&self) -> Option<f32> {
+        return self.critical
+            .filter(|critical| *critical > 0.0)
+            .map(|critical| self.temperature / critical);
+    }
+}
+
+/// Number of processes or threads in each state
+#[derive(Default, Clone, Copy)]
+pub struct StateCounts {
+    pub total: usize,
+    pub running: usize,
+    /// Sleeping, idle or waiting for disk
+    pub sleeping: usize,
+    /// Stopped or traced
+    pub stopped: usize,
+    /// Zombie or dead
+    pub zombie: usize,
+}
+
+impl StateCounts {
+    fn add(&mut self, status: ProcessStatus) {
+        self.total += 1;
+        match status {
+            ProcessStatus::Run => {
+                self.running += 1;
+            }
+            ProcessStatus::Sleep | ProcessStatus::Idle | ProcessStatus::UninterruptibleDiskSleep => {
+                self.sleeping += 1;
+            }
+            ProcessStatus::Stop | ProcessStatus::Tracing => {
+                self.stopped += 1;
+            }
+            ProcessStatus::Zombie | ProcessStatus::Dead => {
+                self.zombie += 1;
+            }
+            _ => (),
+        }
+    }
+}
+
+pub struct HostSnapshot {
+    pub host_name: String,
+    pub kernel_version: String,
+    pub os_version: String,
+    /// Seconds since epoch
+    pub boot_time: u64,
+    /// Seconds since boot
+    pub uptime: u64,
+    /// 1, 5 and 15 minute load averages
+    pub load_average: [f64; 3],
+}
+
+/// System state sampled at one point in time, never modified after it is published
+pub struct Snapshot {
+    /// Milliseconds since epoch when the snapshot was taken
+    pub timestamp_ms: u64,
+    pub processes: Vec<ProcessSnapshot>,
+    pub global_cpu_usage: f32,
+    /// Logical cores in the order the kernel numbers them
+    pub cpus: Vec<CpuSnapshot>,
+    pub used_memory: u64,
+    pub total_memory: u64,
+    pub used_swap: u64,
+    pub total_swap: u64,
+    /// None when no graphics cards have been found
+    pub gpus: Option<Vec<GpuSnapshot>>,
+    /// Mounted filesystems
+    pub disks: Vec<DiskSnapshot>,
+    /// Network interfaces sorted by name
+    pub networks: Vec<NetworkSnapshot>,
+    pub sensors: Vec<SensorSnapshot>,
+    pub host: HostSnapshot,
+    /// Processes including kernel threads
+    pub process_counts: StateCounts,
+    /// Threads of all processes, a process counts as its main thread
+    pub thread_counts: StateCounts,
+}
+
+impl Snapshot {
+    /// Copies the current state of the refreshed system
+    pub fn capture(
+        sys: &System,
+        users: &Users,
+        disks: &Disks,
+        networks: &Networks,
+        components: &Components,
+        gpus: &mut GpuReader
+    ) -> Snapshot {
+        let mut process_counts = StateCounts::default();
+        let mut thread_counts = StateCounts::default();
+        for process in sys.processes().values() {
+            // Threads of userland processes are listed next to their process
+            if process.thread_kind() != Some(ThreadKind::Userland) {
+                process_counts.add(process.status());
+            }
+            thread_counts.add(process.status());
+        }
+        return Snapshot {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_millis() as u64),
+            processes: sys
+                .processes()
+                .iter()
+                .map(|(pid, process)| capture_process(pid, process, users))
+                .collect(),
+            global_cpu_usage: sys.global_cpu_usage(),
+            cpus: sys
+                .cpus()
+                .iter()
+                .map(|cpu| CpuSnapshot { usage: cpu.cpu_usage(), frequency: cpu.frequency() })
+                .collect(),
+            used_memory: sys.used_memory(),
+            total_memory: sys.total_memory(),
+            used_swap: sys.used_swap(),
+            total_swap: sys.total_swap(),
+            gpus: gpus.read(),
+            disks: capture_disks(disks),
+            networks: capture_networks(networks),
+            sensors: components
+                .list()
+                .iter()
+                .map(|component| SensorSnapshot {
+                    label: component.label().to_string(),
+                    temperature: component.temperature(),
+                    max: component.max(),
+                    critical: component.critical(),
+                })
+                .collect(),
+            host: capture_host(),
+            process_counts,
+            thread_counts,
+        };
+    }
+}
+
+fn capture_process(pid: &Pid, process: &Process, users: &Users) -> ProcessSnapshot {
+    let disk_usage = process.disk_usage();
+    return ProcessSnapshot {
+        pid: *pid,
+        name: get_process_name(process),
+        cpu_usage: process.cpu_usage(),
+        memory: process.memory(),
+        virtual_memory: process.virtual_memory(),
+        run_time: process.run_time(),
+        start_time: process.start_time(),
+        user: get_process_user(process, users),
+        status: process.status().to_string(),
+        parent: process.parent(),
+        threads: process.tasks().map_or(1, |tasks| tasks.len().max(1)),
+        read_bytes: disk_usage.read_bytes,
+        written_bytes: disk_usage.written_bytes,
+        command: get_command_line(process),
+        executable: process.exe().map_or(String::new(), |path| path.to_string_lossy().to_string()),
+        nice: read_stat_field(pid, 19).and_then(|nice| nice.parse().ok()),
+    };
+}
+
+fn capture_disk(disk: &Disk, disk_stats: &HashMap<String, (u64, u64)>) -> DiskSnapshot {
+    // Statistics are listed by kernel name, e.g. "/dev/mapper/root" links to "dm-0"
+    let device = fs::canonicalize(disk.name()).unwrap_or_else(|_| disk.name().into());
+    let stats = device
+        .file_name()
+        .and_then(|name| disk_stats.get(name.to_string_lossy().as_ref()));
+    return DiskSnapshot {
+        name: disk.name().to_string_lossy().to_string(),
+        mount_point: disk.mount_point().to_string_lossy().to_string(),
+        file_system: disk.file_system().to_string_lossy().to_string(),
+        total_space: disk.total_space(),
+        available_space: disk.available_space(),
+        total_read_bytes: stats.map(|(read, _)| *read),
+        total_written_bytes: stats.map(|(_, written)| *written),
+    };
+}
+
+fn capture_disks(disks: &Disks) -> Vec<DiskSnapshot> {
+    let disk_stats = read_disk_stats();
+    return disks
+        .list()
+        .iter()
+        .map(|disk| capture_disk(disk, &disk_stats))
+        .collect();
+}
+
+fn capture_network(name: &str, network: &NetworkData) -> NetworkSnapshot {
+    return NetworkSnapshot {
+        name: name.to_string(),
+        total_received: network.total_received(),
+        total_transmitted: network.total_transmitted(),
+        total_packets_received: network.total_packets_received(),
+        total_packets_transmitted: network.total_packets_transmitted(),
+        total_errors_received: network.total_errors_on_received(),
+        total_errors_transmitted: network.total_errors_on_transmitted(),
+    };
+}
+
+fn capture_networks(networks: &Networks) -> Vec<NetworkSnapshot> {
+    let mut snapshots: Vec<NetworkSnapshot> = networks
+        .list()
+        .iter()
+        .map(|(name, network)| capture_network(name, network))
+        .collect();
+    snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+    return snapshots;
+}
+
+fn capture_host() -> HostSnapshot {
+    let load_average = System::load_average();
+    return HostSnapshot {
+        host_name: System::host_name().unwrap_or_default(),
+        kernel_version: System::kernel_version().unwrap_or_default(),
+        os_version: System::long_os_version().unwrap_or_default(),
+        boot_time: System::boot_time(),
+        uptime: System::uptime(),
+        load_average: [load_average.one, load_average.five, load_average.fifteen],
+    };
+}
+
+pub fn get_process_name(process: &Process) -> String {
+    return strip_closing_quotes(&format!("{:?}", process.name()));
+}
+
+pub fn get_process_user(process: &Process, users: &Users) -> String {
+    return match process.user_id() {
+        Some(uid) =>
+            match users.get_user_by_id(uid) {
+                Some(user) => user.name().to_string(),
+                None => uid.to_string(),
+            }
+        None => String::new(),
+    };
+}
+
+fn get_command_line(process: &Process) -> String {
+    let args: Vec<_> = process
+        .cmd()
+        .iter()
+        .map(|arg| arg.to_string_lossy())
+        .collect();
+    return args.join(" ");
+}
@@ -1,21 +1,254 @@
+use std::{ cmp::Ordering, sync::Arc };
+
+use sysinfo::Pid;
+
+use crate::{
+    constants::GRAPH_WINDOW_MS,
+    filter::Filter,
+    format::UnitMode,
+    graph::GraphStyle,
+    history::History,
+    process_signal::ProcessSignal,
+    recording::Replay,
+    sampler::Sampler,
+    snapshot::Snapshot,
+};
+
 pub struct Dimensions {
     pub width: u16,
     pub height: u16,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(PartialEq)]
 pub enum Mode {
     PRINT,
     INPUT,
     EXIT,
     SEARCH,
+    SIGNAL,
+    DETAIL,
 }
 
-pub static mut SELECTED_COLUMN: usize = 0;
-pub static mut SORT_DIRECTION: &str = "DESC";
-pub static mut PREVIOUS_DIMENSIONS: Dimensions = Dimensions {
-    width: 0,
-    height: 0,
-};
-pub static mut MODE: Mode = Mode::PRINT;
-pub static mut SEARCH_TEXT: String = String::new();
+/// What print mode shows below the resource header
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Clone, Copy)]
+pub enum View {
+    PROCESSES,
+    PERFORMANCE,
+    DISKS,
+    NETWORK,
+    SENSORS,
+}
+
+impl View {
+    pub const ALL: [View; 5] = [
+        View::PROCESSES,
+        View::PERFORMANCE,
+        View::DISKS,
+        View::NETWORK,
+        View::SENSORS,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            View::PROCESSES => "processes",
+            View::PERFORMANCE => "performance",
+            View::DISKS => "disks",
+            View::NETWORK => "network",
+            View::SENSORS => "sensors",
+        };
+    }
+
+    pub fn from_name(name: &str) -> Option<View> {
+        return View::ALL.into_iter().find(|view| view.name() == name.to_lowercase());
+    }
+
+    /// View shown after this one when cycling with Tab
+    pub fn next(&self) -> View {
+        let index = View::ALL.iter().position(|view| view == self).unwrap_or(0);
+        return View::ALL[(index + 1) % View::ALL.len()];
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Clone, Copy)]
+pub enum SortDirection {
+    ASC,
+    DESC,
+}
+
+impl SortDirection {
+    pub fn reverse(&self) -> SortDirection {
+        return match self {
+            SortDirection::ASC => SortDirection::DESC,
+            SortDirection::DESC => SortDirection::ASC,
+        };
+    }
+
+    /// Orders by the comparison for ASC, smallest first, and by its reverse for DESC
+    pub fn apply(&self, ordering: Ordering) -> Ordering {
+        return match self {
+            SortDirection::ASC => ordering,
+            SortDirection::DESC => ordering.reverse(),
+        };
+    }
+}
+
+/// Process under the row cursor. PIDs are reused, so the start time tells whether the PID
+/// still belongs to it.
+#[derive(Clone)]
+pub struct SelectedProcess {
+    pub pid: Pid,
+    pub name: String,
+    /// Seconds since the epoch
+    pub start_time: u64,
+}
+
+/// Search input edited on the last row while the process table keeps refreshing
+pub struct SearchSession {
+    pub input: String,
+    pub cursor_position: u16,
+    pub cursor_start_position: u16,
+    pub case_insensitive: bool,
+    pub literal: bool,
+    /// Error of the current input, the last valid filter stays active meanwhile
+    pub error: Option<String>,
+    /// Filter active before the search started, restored on Esc
+    pub previous_filter: Option<Filter>,
+}
+
+/// State shared between run modes, owned by main and passed to every mode
+pub struct App {
+    pub mode: Mode,
+    pub view: View,
+    pub graph_style: GraphStyle,
+    /// Time span of the performance graphs
+    pub graph_window_ms: u64,
+    /// Prefixes of the byte sizes in the process table and header
+    pub unit_mode: UnitMode,
+    pub sampler: Sampler,
+    /// Latest received snapshot, None until the sampler publishes the first one
+    pub snapshot: Option<Arc<Snapshot>>,
+    /// Samples of the received snapshots per process
+    pub history: History,
+    /// Keeps showing the current snapshot while the sampler waits
+    pub paused: bool,
+    /// Index of the selected column in visible_columns
+    pub selected_column: usize,
+    /// Indexes of PROCESS_COLUMNS in the order they are printed
+    pub visible_columns: Vec<usize>,
+    pub sort_direction: SortDirection,
+    pub previous_dimensions: Dimensions,
+    pub search_text: String,
+    /// search_text parsed when the search is submitted, None when there is no filter
+    pub search_filter: Option<Filter>,
+    pub search_case_insensitive: bool,
+    /// Matches search_text as plain text instead of a regular expression
+    pub search_literal: bool,
+    /// Search being typed, None outside search mode
+    pub search_session: Option<SearchSession>,
+    /// Index of the row cursor in the full process list
+    pub selected_row: usize,
+    /// Index of the first printed process
+    pub scroll_offset: usize,
+    /// Number of process rows printed on the last cycle
+    pub process_page_size: usize,
+    /// Process under the row cursor on the last printed cycle
+    pub selected_process: Option<SelectedProcess>,
+    /// Signal chosen from the command line, skips picking the signal in signal mode
+    pub pending_signal: Option<ProcessSignal>,
+    /// Index in DISK_COLUMNS of the column the disks are sorted by
+    pub disk_sort_column: usize,
+    pub disk_sort_direction: SortDirection,
+    /// Index in NETWORK_COLUMNS of the column the interfaces are sorted by
+    pub network_sort_column: usize,
+    pub network_sort_direction: SortDirection,
+    /// Index in SENSOR_COLUMNS of the column the sensors are sorted by
+    pub sensor_sort_column: usize,
+    pub sensor_sort_direction: SortDirection,
+    pub tree_view: bool,
+    /// Shows usage and frequency of every core in the header
+    pub show_cores: bool,
+    /// Shows total network throughput in the header
+    pub show_network: bool,
+    /// Shows the hottest sensor in the header
+    pub show_hottest_sensor: bool,
+    /// Processes whose children are hidden in tree view
+    pub collapsed_pids: Vec<Pid>,
+    /// Only these processes are shown when not empty
+    pub watched_pids: Vec<Pid>,
+    /// Only processes of this user are shown
+    pub user_filter: Option<String>,
+    /// Recording shown instead of the sampler snapshots
+    pub replay: Option<Replay>,
+}
+
+impl App {
+    pub fn new(visible_columns: Vec<usize>, sampler: Sampler) -> App {
+        return App {
+            mode: Mode::PRINT,
+            view: View::PROCESSES,
+            graph_style: GraphStyle::BRAILLE,
+            graph_window_ms: GRAPH_WINDOW_MS,
+            unit_mode: UnitMode::IEC,
+            sampler,
+            snapshot: None,
+            history: History::new(),
+            paused: false,
+            selected_column: 0,
+            visible_columns,
+            sort_direction: SortDirection::ASC,
+            previous_dimensions: Dimensions { width: 0, height: 0 },
+            search_text: String::new(),
+            search_filter: None,
+            search_case_insensitive: false,
+            search_literal: false,
+            search_session: None,
+            selected_row: 0,
+            scroll_offset: 0,
+            process_page_size: 0,
+            selected_process: None,
+            pending_signal: None,
+            disk_sort_column: 0,
+            disk_sort_direction: SortDirection::ASC,
+            network_sort_column: 0,
+            network_sort_direction: SortDirection::ASC,
+            sensor_sort_column: 0,
+            sensor_sort_direction: SortDirection::ASC,
+            tree_view: false,
+            show_cores: false,
+            show_network: false,
+            show_hottest_sensor: false,
+            collapsed_pids: Vec::new(),
+            watched_pids: Vec::new(),
+            user_filter: None,
+            replay: None,
+        };
+    }
+
+    /// Shows the snapshot and adds it to the process history
+    pub fn show_snapshot(&mut self, snapshot: Arc<Snapshot>) {
+        self.history.record(&snapshot);
+        self.snapshot = Some(snapshot);
+    }
+
+    /// Index in PROCESS_COLUMNS of the selected column
+    pub fn selected_column_index(&self) -> usize {
+        return self.visible_columns[self.selected_column];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascending_sorts_smallest_first() {
+        let mut values = [3, 1, 2];
+        values.sort_by(|a, b| SortDirection::ASC.apply(a.cmp(b)));
+        assert_eq!(values, [1, 2, 3]);
+        values.sort_by(|a, b| SortDirection::DESC.apply(a.cmp(b)));
+        assert_eq!(values, [3, 2, 1]);
+    }
+}
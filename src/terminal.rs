@@ -0,0 +1,117 @@
+use std::{
+    cell::Cell,
+    io::stdout,
+    panic::{ self, UnwindSafe },
+    process,
+    sync::atomic::{ AtomicBool, Ordering },
+    thread,
+};
+
+use crossterm::{
+    cursor,
+    execute,
+    terminal::{
+        disable_raw_mode,
+        enable_raw_mode,
+        Clear,
+        ClearType,
+        EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+use signal_hook::{
+    consts::{ SIGINT, SIGSTOP, SIGTERM, SIGTSTP },
+    iterator::Signals,
+    low_level::raise,
+};
+
+thread_local! {
+    /// Set while a panic is expected and caught, the terminal is then left as is
+    static CATCHING_PANIC: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Set when the process is continued after a suspend, until the screen is redrawn
+static RESUMED: AtomicBool = AtomicBool::new(false);
+
+/// Keeps the terminal in raw mode on the alternate screen, restoring it when dropped
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    /// Enters the terminal session and makes sure panics and signals restore it
+    pub fn enter() -> TerminalGuard {
+        install_panic_hook();
+        install_signal_handler();
+        enter_terminal();
+        return TerminalGuard;
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+fn enter_terminal() {
+    enable_raw_mode().expect("failed to enable raw mode");
+    execute!(stdout(), EnterAlternateScreen, cursor::Hide, Clear(ClearType::All)).unwrap();
+}
+
+/// Leaves the alternate screen and raw mode, errors are ignored since this also runs on panic
+fn restore_terminal() {
+    let _ = execute!(stdout(), cursor::Show, LeaveAlternateScreen);
+    let _ = disable_raw_mode();
+}
+
+fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(
+        Box::new(move |info| {
+            if CATCHING_PANIC.get() {
+                return;
+            }
+            // Restore first, otherwise the panic message is lost with the alternate screen
+            restore_terminal();
+            default_hook(info);
+        })
+    );
+}
+
+/// Handles signals sent from outside, since raw mode keeps Ctrl-C and Ctrl-Z from raising them
+fn install_signal_handler() {
+    let mut signals = Signals::new([SIGINT, SIGTERM, SIGTSTP]).expect(
+        "failed to register signal handler"
+    );
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            if signal == SIGTSTP {
+                suspend();
+            } else {
+                restore_terminal();
+                process::exit(128 + signal);
+            }
+        }
+    });
+}
+
+/// Restores the terminal and stops the process, entering the terminal session again when continued.
+/// The screen is cleared on resume, take_resumed then tells the modes to redraw all of it.
+pub fn suspend() {
+    restore_terminal();
+    raise(SIGSTOP).expect("failed to stop process");
+    enter_terminal();
+    RESUMED.store(true, Ordering::SeqCst);
+}
+
+/// Returns true once after the process was continued from a suspend
+pub fn take_resumed() -> bool {
+    return RESUMED.swap(false, Ordering::SeqCst);
+}
+
+/// Runs the function and returns None if it panics, without restoring the terminal or printing the panic
+pub fn catch_quiet_panic<T>(function: impl FnOnce() -> T + UnwindSafe) -> Option<T> {
+    CATCHING_PANIC.set(true);
+    let result = panic::catch_unwind(function);
+    CATCHING_PANIC.set(false);
+    return result.ok();
+}
@@ -1,10 +1,11 @@
-use std::io::{ stdout, Write };
+use std::{ io::{ stdout, Write }, time::Duration };
+use colored::Colorize;
 use crossterm::{ cursor, event::KeyCode, queue };
 use regex::Regex;
 
 use terminal_size::{ terminal_size, Height, Width };
 
-use crate::state::PREVIOUS_DIMENSIONS;
+use crate::state::Dimensions;
 
 pub fn get_terminal_dimensions() -> (u16, u16) {
     let size = terminal_size();
@@ -14,21 +15,17 @@ pub fn get_terminal_dimensions() -> (u16, u16) {
     panic!("Terminal not supported");
 }
 
-pub fn set_current_terminal_dimensions() {
+pub fn set_current_terminal_dimensions(previous_dimensions: &mut Dimensions) {
     let (width, height) = get_terminal_dimensions();
-    unsafe {
-        PREVIOUS_DIMENSIONS.height = height;
-        PREVIOUS_DIMENSIONS.width = width;
-    }
+    previous_dimensions.height = height;
+    previous_dimensions.width = width;
 }
 
-pub fn clear_screen_on_dimension_changed() {
+pub fn clear_screen_on_dimension_changed(previous_dimensions: &mut Dimensions) {
     let (width, height) = get_terminal_dimensions();
-    unsafe {
-        if width != PREVIOUS_DIMENSIONS.width || height != PREVIOUS_DIMENSIONS.height {
-            clearscreen::clear().expect("failed to clear");
-            set_current_terminal_dimensions();
-        }
+    if width != previous_dimensions.width || height != previous_dimensions.height {
+        clearscreen::clear().expect("failed to clear");
+        set_current_terminal_dimensions(previous_dimensions);
     }
 }
 
@@ -40,7 +37,7 @@ pub fn enter_input_mode(input_prefix: &str) -> u16 {
     let cursor_start_position: u16 = input_prefix.chars().count() as u16;
     queue!(stdout(), cursor::MoveTo(cursor_start_position, height)).unwrap();
     queue!(stdout(), cursor::Show).unwrap();
-    stdout().flush().ok().expect("failed to flush");
+    stdout().flush().expect("failed to flush");
     return cursor_start_position;
 }
 
@@ -48,18 +45,18 @@ pub fn print_on_last_row(text: &str) {
     let height = get_terminal_dimensions().1;
     queue!(stdout(), cursor::MoveTo(0, height)).unwrap();
     print!("{}", text);
-    stdout().flush().ok().expect("failed to flush");
+    stdout().flush().expect("failed to flush");
 }
 
 pub fn move_cursor(row: u16, column: u16) {
     queue!(stdout(), cursor::MoveTo(column, row)).unwrap();
-    stdout().flush().ok().expect("failed to flush");
+    stdout().flush().expect("failed to flush");
 }
 
 pub fn print_on_position(text: &str, row: u16, column: u16) {
     move_cursor(row, column);
     print!("{}", text);
-    stdout().flush().ok().expect("failed to flush");
+    stdout().flush().expect("failed to flush");
 }
 
 pub fn empty_row(row: u16) {
@@ -80,7 +77,7 @@ pub fn delete_previous_character(
 ) -> u16 {
     if cursor_position > cursor_start_position {
         let new_position = cursor_position - 1;
-        input.remove((new_position - cursor_start_position) as usize);
+        input.remove(get_byte_index(input, (new_position - cursor_start_position) as usize));
         empty_row(row);
         print_on_position(format!("{input_prefix}{input}").as_str(), row, 0);
         move_cursor(row, new_position);
@@ -101,12 +98,12 @@ pub fn navigate_left_input(row: u16, cursor_position: u16, cursor_start_position
 
 /// Returns new cursor position when moving right while inputting
 pub fn navigate_right_input(
-    input: &String,
+    input: &str,
     row: u16,
     cursor_position: u16,
     cursor_start_position: u16
 ) -> u16 {
-    if cursor_position <= ((input.chars().count() - 1) as u16) + cursor_start_position {
+    if cursor_position < (input.chars().count() as u16) + cursor_start_position {
         let new_position = cursor_position + 1;
         move_cursor(row, new_position);
         return new_position;
@@ -114,12 +111,20 @@ pub fn navigate_right_input(
     return cursor_position;
 }
 
+/// Byte offset of the character at the given character index, input length past the end
+fn get_byte_index(input: &str, char_index: usize) -> usize {
+    return input
+        .char_indices()
+        .nth(char_index)
+        .map_or(input.len(), |(index, _)| index);
+}
+
 fn get_keycode_char(keycode: KeyCode) -> Option<char> {
     return match keycode.to_string().as_str() {
         "Space" => Some(' '),
         str => {
             if str.chars().count() == 1 {
-                return Some(str.chars().nth(0).unwrap());
+                return Some(str.chars().next().unwrap());
             }
             return None;
         }
@@ -136,10 +141,11 @@ pub fn print_input(
 ) -> u16 {
     let char = get_keycode_char(keycode);
 
-    if char.is_some() {
+    if let Some(char) = char {
         let new_position = cursor_position + 1;
-        input.insert((cursor_position - cursor_start_position) as usize, char.unwrap());
-        let text = input.split_at((cursor_position - cursor_start_position) as usize).1;
+        let index = get_byte_index(input, (cursor_position - cursor_start_position) as usize);
+        input.insert(index, char);
+        let text = input.split_at(index).1;
         print_on_position(text, row, cursor_position);
         move_cursor(row, new_position);
         return new_position;
@@ -147,10 +153,23 @@ pub fn print_input(
     return cursor_position;
 }
 
+/// Prints text ending at the right edge of the row, cut off to the terminal width
 pub fn print_at_end_of_row(text: &str, row: u16) {
+    print!("{}", move_to_end_of_row(text, row));
+}
+
+/// Prints an error at the end of the row in red, so it stands apart from information
+pub fn print_error_at_end_of_row(error: &str, row: u16) {
+    print!("{}", move_to_end_of_row(error, row).red());
+}
+
+/// Moves the cursor so the text ends at the end of the row, returns the text cut to the width
+fn move_to_end_of_row(text: &str, row: u16) -> String {
     let width = get_terminal_dimensions().0;
-    queue!(stdout(), cursor::MoveTo(width - (text.chars().count() as u16), row)).unwrap();
-    print!("{}", text);
+    let text = truncate_text(text.to_string(), width);
+    let column = width.saturating_sub(text.chars().count() as u16);
+    queue!(stdout(), cursor::MoveTo(column, row)).unwrap();
+    return text;
 }
 
 pub fn strip_closing_quotes(text: &str) -> String {
@@ -159,17 +178,167 @@ pub fn strip_closing_quotes(text: &str) -> String {
     return format!("{}", result);
 }
 
+/// Cuts text to max_length characters, ending with "..." when there is room for more than it
 pub fn truncate_text(text: String, max_length: u16) -> String {
-    if (text.chars().count() as u16) < max_length {
+    let max_length = max_length as usize;
+    if text.chars().count() <= max_length {
         return text;
     }
     let trailing_characters = "...";
-    let truncate_length = (
-        (max_length as i32) -
-        (text.chars().count() as i32) -
-        (trailing_characters.chars().count() as i32)
-    ).abs() as u16;
-    let re = Regex::new(format!(".{}{}{}$", "{", truncate_length, "}").as_str()).unwrap();
-    let result = re.replace_all(text.as_str(), trailing_characters);
-    return format!("{}", result);
+    if max_length <= trailing_characters.len() {
+        return text.chars().take(max_length).collect();
+    }
+    let kept: String = text.chars().take(max_length - trailing_characters.len()).collect();
+    return format!("{}{}", kept, trailing_characters);
+}
+
+/// Formats seconds since UNIX epoch as UTC date and time
+pub fn format_timestamp(seconds: u64) -> String {
+    // Days to civil date conversion from http://howardhinnant.github.io/date_algorithms.html
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + (if month <= 2 { 1 } else { 0 });
+    let time = seconds % 86400;
+    return format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        (time % 3600) / 60,
+        time % 60
+    );
+}
+
+/// Parses UTC times as "YYYY-MM-DD HH:MM:SS" or "HH:MM:SS" into seconds since UNIX epoch,
+/// a time without a date is taken on the day of reference_seconds
+pub fn parse_timestamp(text: &str, reference_seconds: u64) -> Result<u64, String> {
+    let text = text.trim();
+    let error = || format!("Invalid time '{text}', expected YYYY-MM-DD HH:MM:SS or HH:MM:SS");
+    let parse_parts = |part: &str, separator: char| -> Option<Vec<i64>> {
+        let parts: Option<Vec<i64>> = part
+            .split(separator)
+            .map(|number| number.parse().ok())
+            .collect();
+        return parts.filter(|parts| parts.len() == 3);
+    };
+    let (date, time) = match text.split_once(' ') {
+        Some((date, time)) => (Some(date), time.trim()),
+        None => (None, text),
+    };
+    let time = parse_parts(time, ':').ok_or_else(error)?;
+    if time[0] > 23 || time[1] > 59 || time[2] > 59 || time.iter().any(|part| *part < 0) {
+        return Err(error());
+    }
+    let days = match date {
+        Some(date) => {
+            let date = parse_parts(date, '-').ok_or_else(error)?;
+            let (year, month, day) = (date[0], date[1], date[2]);
+            if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+                return Err(error());
+            }
+            // Inverse of the conversion in format_timestamp
+            let year = if month <= 2 { year - 1 } else { year };
+            let era = year.div_euclid(400);
+            let year_of_era = year - era * 400;
+            let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+            let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+            era * 146097 + day_of_era - 719468
+        }
+        None => (reference_seconds / 86400) as i64,
+    };
+    let seconds = days * 86400 + time[0] * 3600 + time[1] * 60 + time[2];
+    return u64::try_from(seconds).map_err(|_| error());
+}
+
+/// Parses durations like "500ms", "2s", "1.5s" or "1m", plain numbers are seconds
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let (number, unit_ms) = if let Some(number) = text.strip_suffix("ms") {
+        (number, 1.0)
+    } else if let Some(number) = text.strip_suffix('s') {
+        (number, 1000.0)
+    } else if let Some(number) = text.strip_suffix('m') {
+        (number, 60_000.0)
+    } else {
+        (text, 1000.0)
+    };
+    return match number.trim().parse::<f64>() {
+        Ok(number) if number >= 0.0 && number.is_finite() => {
+            Ok(Duration::from_millis((number * unit_ms).round() as u64))
+        }
+        _ => Err(format!("Invalid duration '{text}', expected e.g. 500ms, 2s or 1m")),
+    };
+}
+
+/// Formats duration in the format accepted by parse_duration, e.g. "500ms", "1.5s" or "2m"
+pub fn format_duration(duration: Duration) -> String {
+    let ms = duration.as_millis();
+    if ms < 1000 {
+        return format!("{ms}ms");
+    }
+    if ms.is_multiple_of(60_000) {
+        return format!("{}m", ms / 60_000);
+    }
+    return format!("{}s", (ms as f64) / 1000.0);
+}
+
+/// Splits text into lines of at most given width
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() || width == 0 {
+        return vec![String::new()];
+    }
+    return chars
+        .chunks(width)
+        .map(|chunk| chunk.iter().collect())
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn navigate_right_input_stays_on_empty_input() {
+        assert_eq!(navigate_right_input("", 0, 2, 2), 2);
+    }
+
+    #[test]
+    fn navigate_right_input_stops_after_last_character() {
+        assert_eq!(navigate_right_input("café", 0, 6, 2), 6);
+    }
+
+    #[test]
+    fn get_byte_index_counts_characters() {
+        assert_eq!(get_byte_index("café", 3), 3);
+        assert_eq!(get_byte_index("café", 4), 5);
+        assert_eq!(get_byte_index("ünï", 1), 2);
+        assert_eq!(get_byte_index("", 0), 0);
+    }
+
+    #[test]
+    fn truncate_text_keeps_short_text() {
+        assert_eq!(truncate_text(String::from("memory"), 6), "memory");
+        assert_eq!(truncate_text(String::from("mem"), 10), "mem");
+    }
+
+    #[test]
+    fn truncate_text_ends_with_dots() {
+        assert_eq!(truncate_text(String::from("Virtual memory"), 8), "Virtu...");
+        assert_eq!(truncate_text(String::from("ünïcödé text"), 7), "ünïc...");
+    }
+
+    #[test]
+    fn truncate_text_cuts_without_room_for_dots() {
+        assert_eq!(truncate_text(String::from("Memory"), 3), "Mem");
+        assert_eq!(truncate_text(String::from("Memory"), 1), "M");
+        assert_eq!(truncate_text(String::from("Memory"), 0), "");
+    }
 }